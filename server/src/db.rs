@@ -0,0 +1,172 @@
+use std::fs;
+use std::path::PathBuf;
+use chrono::NaiveDateTime;
+use serde_json;
+use web_snake::snake::GameResult;
+use web_snake::leaderboard::{Leaderboard, LeaderboardScore};
+
+// `Database::open` won't load a leaderboard file with any other version, an old
+// file has to be converted by hand before the server starts again
+const DATABASE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    // utc, when the server accepted it
+    pub date: NaiveDateTime,
+    pub result: GameResult,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DatabaseFile {
+    version: u32,
+    entries: Vec<Entry>,
+}
+
+// every verified score, kept in memory and written out in full on each change
+pub struct Database {
+    path: PathBuf,
+    file: DatabaseFile,
+}
+
+impl Database {
+    // starts a new database if there isn't one at the path yet
+    pub fn open(path: PathBuf) -> Result<Database, String> {
+        if !path.exists() {
+            return Ok(Database {
+                path,
+                file: DatabaseFile {
+                    version: DATABASE_VERSION,
+                    entries: Vec::new(),
+                },
+            });
+        }
+        let json = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let file: DatabaseFile = serde_json::from_str(&json).map_err(|e| format!("{} is corrupted: {}", path.display(), e))?;
+        if file.version != DATABASE_VERSION {
+            return Err(format!("{} is version {}, expected version {}", path.display(), file.version, DATABASE_VERSION));
+        }
+        Ok(Database { path, file })
+    }
+
+    // the same replay can't be submitted twice
+    pub fn contains(&self, result: &GameResult) -> bool {
+        self.file.entries.iter()
+            .any(|e| e.result.settings.seed == result.settings.seed && e.result.history == result.history)
+    }
+
+    // returns where the new entry places on its configuration's leaderboard
+    pub fn insert(&mut self, entry: Entry) -> Result<usize, String> {
+        let configuration = entry.result.configuration();
+        let score = entry.result.score;
+        self.file.entries.push(entry);
+        if let Err(e) = self.save() {
+            self.file.entries.pop();
+            return Err(e);
+        }
+        let better = self.file.entries.iter()
+            .filter(|e| e.result.score > score && e.result.configuration() == configuration)
+            .count();
+        Ok(better + 1)
+    }
+
+    // written to the side and moved into place so a crash can't leave half a file behind
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string(&self.file).map_err(|e| format!("failed to serialize scores: {}", e))?;
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, json).map_err(|e| format!("failed to write {}: {}", temp.display(), e))?;
+        fs::rename(&temp, &self.path).map_err(|e| format!("failed to replace {}: {}", self.path.display(), e))
+    }
+
+    // the best `limit` scores of every configuration, configurations in alphabetical order
+    pub fn leaderboards(&self, limit: usize) -> Vec<Leaderboard> {
+        let mut leaderboards: Vec<Leaderboard> = Vec::new();
+        for entry in self.file.entries.iter() {
+            let configuration = entry.result.configuration();
+            let score = LeaderboardScore {
+                name: entry.name.clone(),
+                score: entry.result.score,
+                apples_eaten: entry.result.apples_eaten,
+                turns_passed: entry.result.turns_passed,
+                date: entry.date,
+            };
+            match leaderboards.iter().position(|l| l.configuration == configuration) {
+                Some(i) => leaderboards[i].scores.push(score),
+                None => leaderboards.push(Leaderboard {
+                    configuration,
+                    scores: vec![score],
+                }),
+            }
+        }
+        for leaderboard in leaderboards.iter_mut() {
+            // ties go to whoever got there first
+            leaderboard.scores.sort_by(|a, b| b.score.cmp(&a.score).then(a.date.cmp(&b.date)));
+            leaderboard.scores.truncate(limit);
+        }
+        leaderboards.sort_by(|a, b| a.configuration.cmp(&b.configuration));
+        leaderboards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+    use chrono::NaiveDate;
+    use web_snake::game_mode::GameMode;
+    use web_snake::snake::{GameSettings, SnakeGameLogic};
+
+    // somewhere to write that won't collide with other tests running at the same time
+    fn temporary(name: &str) -> Database {
+        let path = env::temp_dir().join(format!("snake-leaderboard-{}-{}.json", process::id(), name));
+        let _ = fs::remove_file(&path);
+        Database::open(path).unwrap()
+    }
+
+    fn entry(name: &str, day: u32, settings: GameSettings, score: u32) -> Entry {
+        let mut result = SnakeGameLogic::new(settings).get_results();
+        result.score = score;
+        Entry {
+            name: name.to_string(),
+            date: NaiveDate::from_ymd(2018, 11, day).and_hms(12, 0, 0),
+            result,
+        }
+    }
+
+    #[test]
+    fn ranks_against_the_same_configuration_only() {
+        let mut db = temporary("ranks");
+        let zen = GameSettings { mode: GameMode::Zen, ..GameSettings::default() };
+        assert_eq!(db.insert(entry("a", 1, GameSettings::default(), 5)), Ok(1));
+        assert_eq!(db.insert(entry("b", 2, GameSettings::default(), 3)), Ok(2));
+        assert_eq!(db.insert(entry("c", 3, GameSettings::default(), 8)), Ok(1));
+        assert_eq!(db.insert(entry("d", 4, zen, 1)), Ok(1));
+        let _ = fs::remove_file(&db.path);
+    }
+
+    #[test]
+    fn leaderboards_are_sorted_and_cut_off() {
+        let mut db = temporary("leaderboards");
+        db.insert(entry("first", 1, GameSettings::default(), 5)).unwrap();
+        db.insert(entry("low", 2, GameSettings::default(), 1)).unwrap();
+        db.insert(entry("high", 3, GameSettings::default(), 9)).unwrap();
+        db.insert(entry("tied", 4, GameSettings::default(), 5)).unwrap();
+        let leaderboards = db.leaderboards(3);
+        assert_eq!(leaderboards.len(), 1);
+        let names: Vec<&str> = leaderboards[0].scores.iter().map(|s| s.name.as_ref()).collect();
+        // ties go to whoever got there first
+        assert_eq!(names, vec!["high", "first", "tied"]);
+        let _ = fs::remove_file(&db.path);
+    }
+
+    #[test]
+    fn scores_survive_reopening() {
+        let mut db = temporary("reopen");
+        db.insert(entry("a", 1, GameSettings::default(), 5)).unwrap();
+        let reopened = Database::open(db.path.clone()).unwrap();
+        assert!(reopened.contains(&db.file.entries[0].result));
+        assert_eq!(reopened.leaderboards(10)[0].scores[0].score, 5);
+        let _ = fs::remove_file(&db.path);
+    }
+}
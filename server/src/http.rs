@@ -0,0 +1,154 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use serde::Serialize;
+use serde_json;
+
+// replays are small, anything much bigger than this isn't a real submission
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const READ_TIMEOUT_SECS: u64 = 10;
+
+// just enough http to talk to the page, one request per connection
+pub struct Request {
+    pub method: String,
+    // without the query string
+    pub path: String,
+    // the site the page making the request came from, browsers always send it cross origin
+    pub origin: Option<String>,
+    pub body: Vec<u8>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json<T: Serialize>(status: u16, body: &T) -> Response {
+        Response {
+            status,
+            body: serde_json::to_string(body).expect("failed to serialize response"),
+        }
+    }
+
+    pub fn empty(status: u16) -> Response {
+        Response {
+            status,
+            body: String::new(),
+        }
+    }
+}
+
+pub fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT_SECS)))
+        .map_err(|e| format!("failed to set read timeout: {}", e))?;
+    parse_request(BufReader::new(stream))
+}
+
+fn parse_request<R: BufRead>(mut reader: R) -> Result<Request, String> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| format!("failed to read request: {}", e))?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or_else(|| String::from("empty request"))?.to_string();
+    let target = parts.next().ok_or_else(|| String::from("request is missing a path"))?;
+    let path = target.split('?').next().unwrap_or("").to_string();
+
+    let mut content_length = 0;
+    let mut origin = None;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| format!("failed to read headers: {}", e))?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        let mut split = header.splitn(2, ':');
+        let name = split.next().unwrap_or("").trim();
+        let value = split.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().map_err(|_| format!("invalid content length \"{}\"", value))?;
+        } else if name.eq_ignore_ascii_case("origin") {
+            origin = Some(value.to_string());
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err(format!("request body is {} bytes, the limit is {}", content_length, MAX_BODY_BYTES));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| format!("failed to read request body: {}", e))?;
+    Ok(Request { method, path, origin, body })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+// the page is served from somewhere else, only that one origin gets to read the responses
+pub fn write_response<W: Write>(mut stream: W, response: &Response, origin: &str) -> io::Result<()> {
+    write!(stream, "HTTP/1.1 {} {}\r\n", response.status, reason(response.status))?;
+    write!(stream, "Content-Type: application/json\r\n")?;
+    write!(stream, "Content-Length: {}\r\n", response.body.len())?;
+    write!(stream, "Access-Control-Allow-Origin: {}\r\n", origin)?;
+    write!(stream, "Vary: Origin\r\n")?;
+    write!(stream, "Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n")?;
+    write!(stream, "Access-Control-Allow-Headers: Content-Type\r\n")?;
+    write!(stream, "Connection: close\r\n\r\n")?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn parse(text: &str) -> Result<Request, String> {
+        parse_request(Cursor::new(text.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn reads_the_request_line_headers_and_body() {
+        let request = parse("POST /scores?x=1 HTTP/1.1\r\nOrigin: http://localhost:8000\r\nContent-Length: 4\r\n\r\n{}{}").unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/scores");
+        assert_eq!(request.origin, Some(String::from("http://localhost:8000")));
+        assert_eq!(request.body, b"{}{}".to_vec());
+    }
+
+    #[test]
+    fn headers_are_case_insensitive() {
+        let request = parse("GET /leaderboards HTTP/1.1\r\ncontent-length: 0\r\nORIGIN: http://a\r\n\r\n").unwrap();
+        assert_eq!(request.origin, Some(String::from("http://a")));
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn broken_requests_are_rejected() {
+        assert!(parse("").is_err());
+        assert!(parse("GET\r\n\r\n").is_err());
+        assert!(parse("POST /scores HTTP/1.1\r\nContent-Length: lots\r\n\r\n").is_err());
+        assert!(parse("POST /scores HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort").is_err());
+        let huge = format!("POST /scores HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_BYTES + 1);
+        assert!(parse(&huge).is_err());
+    }
+
+    #[test]
+    fn responses_only_allow_the_one_origin() {
+        let mut out = Vec::new();
+        write_response(&mut out, &Response::empty(204), "http://localhost:8000").unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(text.contains("Access-Control-Allow-Origin: http://localhost:8000\r\n"));
+        assert!(!text.contains("*"));
+    }
+}
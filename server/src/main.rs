@@ -0,0 +1,253 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate chrono;
+extern crate web_snake;
+
+use std::env;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process;
+use chrono::Utc;
+use web_snake::leaderboard::{Submission, Accepted, Rejected};
+use web_snake::replay;
+
+mod http;
+mod db;
+
+use http::{Request, Response};
+use db::{Database, Entry};
+
+const DEFAULT_PORT: u16 = 8787;
+const DEFAULT_DATABASE: &str = "leaderboard.json";
+// where `cargo web start` serves the page
+const DEFAULT_ORIGIN: &str = "http://localhost:8000";
+// how many scores each configuration's leaderboard shows
+const LEADERBOARD_SIZE: usize = 10;
+const MAX_NAME_LENGTH: usize = 24;
+
+const USAGE: &str = "usage: snake-leaderboard [--port PORT] [--db FILE] [--origin URL]";
+
+struct Options {
+    port: u16,
+    database: PathBuf,
+    // the only site whose pages get to talk to the leaderboard
+    origin: String,
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1).collect()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    let Options { port, database, origin } = options;
+    let mut db = match Database::open(database) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    // only ever listen on localhost, this isn't meant to face the internet
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("failed to listen on port {}: {}", port, e);
+            process::exit(1);
+        }
+    };
+    println!("leaderboard listening on http://127.0.0.1:{} for pages from {}", port, origin);
+
+    // one request at a time keeps the database simple, and it's plenty for a team
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => handle(&mut db, &stream, &origin),
+            Err(e) => eprintln!("failed to accept connection: {}", e),
+        }
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        port: DEFAULT_PORT,
+        database: PathBuf::from(DEFAULT_DATABASE),
+        origin: DEFAULT_ORIGIN.to_string(),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_ref() {
+            "--port" => options.port = value.parse().map_err(|_| format!("\"{}\" is not a port", value))?,
+            "--db" => options.database = PathBuf::from(value),
+            // browsers send origins without a trailing slash
+            "--origin" => options.origin = value.trim_end_matches('/').to_string(),
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn handle(db: &mut Database, stream: &TcpStream, origin: &str) {
+    let response = respond(db, http::read_request(stream), origin);
+    if let Err(e) = http::write_response(stream, &response, origin) {
+        eprintln!("failed to send response: {}", e);
+    }
+}
+
+fn respond(db: &mut Database, request: Result<Request, String>, origin: &str) -> Response {
+    match request {
+        Ok(ref request) if request.origin.as_ref().map_or(false, |o| o != origin) => {
+            // any other site the player has open could otherwise post scores in their name
+            Response::json(403, &Rejected { error: format!("only pages from {} can use this leaderboard", origin) })
+        }
+        Ok(request) => route(db, &request),
+        Err(error) => Response::json(400, &Rejected { error }),
+    }
+}
+
+fn route(db: &mut Database, request: &Request) -> Response {
+    match (request.method.as_ref(), request.path.as_ref()) {
+        // browsers check before posting json to another origin
+        ("OPTIONS", _) => Response::empty(204),
+        ("GET", "/leaderboards") => Response::json(200, &db.leaderboards(LEADERBOARD_SIZE)),
+        ("POST", "/scores") => match submit(db, &request.body) {
+            Ok(accepted) => Response::json(201, &accepted),
+            Err((status, error)) => Response::json(status, &Rejected { error }),
+        },
+        _ => Response::json(404, &Rejected { error: format!("nothing at {} {}", request.method, request.path) }),
+    }
+}
+
+// a score only goes in once its replay has been played back and comes out the same
+fn submit(db: &mut Database, body: &[u8]) -> Result<Accepted, (u16, String)> {
+    let submission: Submission = serde_json::from_slice(body)
+        .map_err(|e| (400, format!("invalid submission: {}", e)))?;
+    let name = match submission.name.trim() {
+        "" => "anonymous",
+        name => name,
+    };
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err((400, format!("names can be at most {} characters", MAX_NAME_LENGTH)));
+    }
+    let result = submission.result;
+    if result.practice || result.settings.practice {
+        return Err((400, String::from("practice games don't go on the leaderboard")));
+    }
+    if db.contains(&result) {
+        return Err((409, String::from("this game has already been submitted")));
+    }
+    replay::verify(&result).map_err(|e| (400, format!("replay doesn't check out: {}", e)))?;
+
+    let configuration = result.configuration();
+    let score = result.score;
+    let rank = db.insert(Entry {
+        name: name.to_string(),
+        date: Utc::now().naive_utc(),
+        result,
+    }).map_err(|e| (500, e))?;
+    println!("{} scored {} on {}, rank {}", name, score, configuration, rank);
+    Ok(Accepted { configuration, rank })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use web_snake::game_mode::GameMode;
+    use web_snake::snake::{GameResult, GameSettings, MoveDirection, SnakeGameLogic};
+
+    const ORIGIN: &str = "http://localhost:8000";
+
+    fn temporary(name: &str) -> (Database, PathBuf) {
+        let path = env::temp_dir().join(format!("snake-leaderboard-{}-main-{}.json", process::id(), name));
+        let _ = fs::remove_file(&path);
+        (Database::open(path.clone()).unwrap(), path)
+    }
+
+    // sweeps the board row by row, the walls wrap around in zen so it never dies
+    fn play(settings: GameSettings) -> GameResult {
+        let mut game = SnakeGameLogic::new(settings);
+        for turn in 0..100 {
+            match turn % 9 {
+                7 => game.press_key(MoveDirection::Down),
+                8 => game.press_key(MoveDirection::Right),
+                _ => {}
+            }
+            game.tick();
+        }
+        // zen games only ever end with the player stopping them
+        game.finish();
+        game.get_results()
+    }
+
+    fn zen() -> GameSettings {
+        GameSettings { mode: GameMode::Zen, seed: 3, ..GameSettings::default() }
+    }
+
+    fn body(result: GameResult) -> Vec<u8> {
+        serde_json::to_vec(&Submission { name: String::from("tester"), result }).unwrap()
+    }
+
+    fn error(rejected: Result<Accepted, (u16, String)>) -> u16 {
+        match rejected {
+            Ok(_) => panic!("submission should have been rejected"),
+            Err((status, _)) => status,
+        }
+    }
+
+    #[test]
+    fn real_games_go_on_once() {
+        let (mut db, path) = temporary("accept");
+        let result = play(zen());
+        let accepted = submit(&mut db, &body(result.clone())).map_err(|e| e.1).unwrap();
+        assert_eq!(accepted.rank, 1);
+        assert_eq!(accepted.configuration, result.configuration());
+        assert_eq!(error(submit(&mut db, &body(result))), 409);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn practice_games_are_rejected() {
+        let (mut db, _) = temporary("practice");
+        let result = play(GameSettings { practice: true, ..zen() });
+        assert_eq!(error(submit(&mut db, &body(result))), 400);
+    }
+
+    #[test]
+    fn oversized_boards_are_rejected() {
+        let (mut db, _) = temporary("oversized");
+        let mut result = play(zen());
+        result.settings.width = 100_000;
+        result.settings.height = 100_000;
+        assert_eq!(error(submit(&mut db, &body(result))), 400);
+    }
+
+    #[test]
+    fn forged_scores_are_rejected() {
+        let (mut db, _) = temporary("forged");
+        let mut result = play(zen());
+        result.score += 10;
+        assert_eq!(error(submit(&mut db, &body(result))), 400);
+        assert!(db.leaderboards(10).is_empty());
+    }
+
+    #[test]
+    fn other_origins_are_turned_away() {
+        let (mut db, _) = temporary("origins");
+        let request = |origin: Option<&str>| Request {
+            method: String::from("GET"),
+            path: String::from("/leaderboards"),
+            origin: origin.map(String::from),
+            body: Vec::new(),
+        };
+        assert_eq!(respond(&mut db, Ok(request(Some("http://evil.example"))), ORIGIN).status, 403);
+        assert_eq!(respond(&mut db, Ok(request(Some(ORIGIN))), ORIGIN).status, 200);
+        // curl and friends don't send one at all
+        assert_eq!(respond(&mut db, Ok(request(None)), ORIGIN).status, 200);
+    }
+}
@@ -0,0 +1,61 @@
+use stdweb::unstable::TryInto;
+use stdweb::web::{document, IParentNode, INode};
+use events::{DeathCause, GameEvent};
+use storage;
+
+const ACCESSIBILITY_KEY: &str = "accessibility";
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct AccessibilitySettings {
+    // the snake jumps from square to square instead of sliding between them
+    pub reduced_motion: bool,
+    // read the score and any danger out through the page's live region
+    pub announce: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        AccessibilitySettings {
+            reduced_motion: prefers_reduced_motion(),
+            announce: false,
+        }
+    }
+}
+
+// whatever the operating system has been told, until the player says otherwise
+fn prefers_reduced_motion() -> bool {
+    js! {
+        return !!(window.matchMedia && window.matchMedia("(prefers-reduced-motion: reduce)").matches);
+    }.try_into().unwrap_or(false)
+}
+
+pub fn load_settings() -> AccessibilitySettings {
+    storage::load(ACCESSIBILITY_KEY).unwrap_or_default()
+}
+
+pub fn save_settings(settings: AccessibilitySettings) -> Result<(), String> {
+    storage::save(ACCESSIBILITY_KEY, &settings)
+}
+
+// what a screen reader should say about something that happened, most things aren't worth it
+pub fn announcement(event: &GameEvent) -> Option<String> {
+    match *event {
+        GameEvent::AppleEaten { apples_eaten: 1 } => Some(String::from("1 apple")),
+        GameEvent::AppleEaten { apples_eaten } => Some(format!("{} apples", apples_eaten)),
+        GameEvent::Died(DeathCause::Wall) => Some(String::from("crashed into the wall")),
+        GameEvent::Died(DeathCause::Itself) => Some(String::from("crashed into yourself")),
+        GameEvent::Died(DeathCause::Hazard) => Some(String::from("crashed into a hazard")),
+        GameEvent::DangerAhead => Some(String::from("danger ahead")),
+        GameEvent::SpeedChanged { .. } => Some(String::from("speeding up")),
+        _ => None,
+    }
+}
+
+// screen readers pick up anything written into the live region, the same text
+// twice in a row doesn't get read again so danger isn't repeated every turn
+pub fn announce(text: &str) {
+    let announcer = document().query_selector("#announcer").unwrap().unwrap();
+    if announcer.text_content().as_ref().map(|t| t.as_str()) != Some(text) {
+        announcer.set_text_content(text);
+    }
+}
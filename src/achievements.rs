@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::time::Duration as StdDuration;
+use chrono::NaiveDate;
+use daily;
+use events::GameEvent;
+use game_mode::GameMode;
+use snake::{GameResult, GameEnd};
+use storage;
+
+const ACHIEVEMENTS_KEY: &str = "achievements";
+
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Achievement {
+    Glutton,
+    FullBoard,
+    Survivor,
+    StraightShooter,
+    TorusWinner,
+}
+
+pub const ALL_ACHIEVEMENTS: [Achievement; 5] = [
+    Achievement::Glutton,
+    Achievement::FullBoard,
+    Achievement::Survivor,
+    Achievement::StraightShooter,
+    Achievement::TorusWinner,
+];
+
+impl Achievement {
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::Glutton => "Glutton",
+            Achievement::FullBoard => "Full House",
+            Achievement::Survivor => "Survivor",
+            Achievement::StraightShooter => "Straight Shooter",
+            Achievement::TorusWinner => "Donut Champion",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::Glutton => "Eat 50 apples in one game",
+            Achievement::FullBoard => "Fill an 8x6 board",
+            Achievement::Survivor => "Survive for 5 minutes",
+            Achievement::StraightShooter => "Eat 3 apples without turning",
+            Achievement::TorusWinner => "Fill the board in zen mode, where the edges wrap around",
+        }
+    }
+}
+
+// everything that can be worked out once the game is over
+pub fn earned_by(r: &GameResult) -> Vec<Achievement> {
+    let mut earned = Vec::new();
+    if r.practice || r.settings.practice {
+        return earned;
+    }
+    let cleared = r.end == Some(GameEnd::Cleared);
+    // puzzles end once their apples run out, which isn't the same as filling the board
+    let puzzle = match r.settings.mode {
+        GameMode::Puzzle { .. } => true,
+        _ => false,
+    };
+    if r.apples_eaten >= 50 {
+        earned.push(Achievement::Glutton);
+    }
+    if cleared && !puzzle && r.width == 8 && r.height == 6 {
+        earned.push(Achievement::FullBoard);
+    }
+    if r.time_played >= StdDuration::from_secs(5 * 60) {
+        earned.push(Achievement::Survivor);
+    }
+    if cleared && r.settings.mode.wraps_edges() {
+        earned.push(Achievement::TorusWinner);
+    }
+    earned
+}
+
+// watches the events of the game being played for achievements
+// that depend on how the game went rather than how it ended
+pub struct Tracker {
+    counting: bool,
+    apples_since_turn: u32,
+}
+
+impl Tracker {
+    pub fn new() -> Self {
+        Tracker {
+            counting: false,
+            apples_since_turn: 0,
+        }
+    }
+
+    pub fn start_game(&mut self, practice: bool) {
+        self.counting = !practice;
+        self.apples_since_turn = 0;
+    }
+
+    pub fn on_event(&mut self, event: &GameEvent) -> Option<Achievement> {
+        if !self.counting {
+            return None;
+        }
+        match *event {
+            GameEvent::Turned { .. } => {
+                self.apples_since_turn = 0;
+                None
+            }
+            GameEvent::AppleEaten { .. } => {
+                self.apples_since_turn += 1;
+                if self.apples_since_turn == 3 {
+                    Some(Achievement::StraightShooter)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn load_unlocked() -> BTreeMap<Achievement, NaiveDate> {
+    storage::load(ACHIEVEMENTS_KEY).unwrap_or_else(BTreeMap::new)
+}
+
+// saves the achievements and hands back the ones that weren't already unlocked
+pub fn unlock(achievements: &[Achievement]) -> Result<Vec<Achievement>, String> {
+    let mut unlocked = load_unlocked();
+    let mut new = Vec::new();
+    for achievement in achievements.iter() {
+        if !unlocked.contains_key(achievement) {
+            unlocked.insert(*achievement, daily::today());
+            new.push(*achievement);
+        }
+    }
+    if !new.is_empty() {
+        storage::save(ACHIEVEMENTS_KEY, &unlocked)?;
+    }
+    Ok(new)
+}
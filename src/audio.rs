@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use stdweb::Value;
+use stdweb::unstable::TryInto;
+use events::GameEvent;
+use storage;
+
+const AUDIO_SETTINGS_KEY: &str = "audio-settings";
+
+#[derive(Clone, Copy)]
+pub enum Wave {
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+}
+
+impl Wave {
+    // the name web audio uses for this oscillator type
+    fn name(self) -> &'static str {
+        match self {
+            Wave::Sine => "sine",
+            Wave::Square => "square",
+            Wave::Triangle => "triangle",
+            Wave::Sawtooth => "sawtooth",
+        }
+    }
+}
+
+// a single oscillator note, sliding from one pitch to another and fading out
+#[derive(Clone, Copy)]
+pub struct Tone {
+    wave: Wave,
+    start_hz: f64,
+    end_hz: f64,
+    // seconds after the sound starts that this tone begins
+    delay: f64,
+    duration: f64,
+    // 0 <= gain <= 1 :: loudness relative to the other tones
+    gain: f64,
+}
+
+fn tone(wave: Wave, start_hz: f64, end_hz: f64, delay: f64, duration: f64, gain: f64) -> Tone {
+    Tone {
+        wave,
+        start_hz,
+        end_hz,
+        delay,
+        duration,
+        gain,
+    }
+}
+
+// quick rising blip
+pub fn eat_chirp() -> Vec<Tone> {
+    vec![
+        tone(Wave::Square, 660.0, 1320.0, 0.0, 0.08, 0.4),
+        tone(Wave::Sine, 1320.0, 1760.0, 0.06, 0.06, 0.3),
+    ]
+}
+
+pub fn turn_tick() -> Vec<Tone> {
+    vec![tone(Wave::Triangle, 220.0, 180.0, 0.0, 0.03, 0.2)]
+}
+
+// long slide down into a low buzz
+pub fn death() -> Vec<Tone> {
+    vec![
+        tone(Wave::Sawtooth, 440.0, 110.0, 0.0, 0.4, 0.5),
+        tone(Wave::Square, 110.0, 55.0, 0.35, 0.3, 0.4),
+    ]
+}
+
+// three notes going up
+pub fn speed_up() -> Vec<Tone> {
+    vec![
+        tone(Wave::Square, 523.0, 523.0, 0.0, 0.07, 0.3),
+        tone(Wave::Square, 659.0, 659.0, 0.08, 0.07, 0.3),
+        tone(Wave::Square, 784.0, 784.0, 0.16, 0.1, 0.3),
+    ]
+}
+
+pub fn sound_for(event: &GameEvent) -> Option<Vec<Tone>> {
+    match *event {
+        GameEvent::AppleEaten { .. } => Some(eat_chirp()),
+        GameEvent::Turned { .. } => Some(turn_tick()),
+        GameEvent::Died(_) => Some(death()),
+        GameEvent::SpeedChanged { .. } => Some(speed_up()),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct AudioSettings {
+    // 0 <= volume <= 1
+    pub volume: f64,
+    pub muted: bool,
+}
+
+pub struct Audio {
+    settings: RefCell<AudioSettings>,
+    // browsers won't let us make sounds until the player has interacted with
+    // the page, so the audio context gets made the first time something plays
+    context: RefCell<Option<Value>>,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        Audio {
+            settings: RefCell::new(storage::load(AUDIO_SETTINGS_KEY).unwrap_or(AudioSettings {
+                volume: 0.5,
+                muted: false,
+            })),
+            context: RefCell::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> AudioSettings {
+        *self.settings.borrow()
+    }
+
+    pub fn set_settings(&self, settings: AudioSettings) -> Result<(), String> {
+        *self.settings.borrow_mut() = settings;
+        storage::save(AUDIO_SETTINGS_KEY, &settings)
+    }
+
+    pub fn play(&self, tones: &[Tone]) {
+        let settings = self.settings();
+        if settings.muted || settings.volume <= 0.0 {
+            return;
+        }
+        let context = match self.context() {
+            Some(c) => c,
+            None => return,
+        };
+        for t in tones.iter() {
+            js! { @(no_return)
+                var ctx = @{&context};
+                var start = ctx.currentTime + @{t.delay};
+                var end = start + @{t.duration};
+                var osc = ctx.createOscillator();
+                var gain = ctx.createGain();
+                osc.type = @{t.wave.name()};
+                osc.frequency.setValueAtTime(@{t.start_hz}, start);
+                osc.frequency.exponentialRampToValueAtTime(@{t.end_hz}, end);
+                gain.gain.setValueAtTime(@{t.gain * settings.volume}, start);
+                gain.gain.exponentialRampToValueAtTime(0.0001, end);
+                osc.connect(gain);
+                gain.connect(ctx.destination);
+                osc.start(start);
+                osc.stop(end);
+            }
+        }
+    }
+
+    fn context(&self) -> Option<Value> {
+        let mut context = self.context.borrow_mut();
+        if context.is_none() {
+            let created = js! {
+                var AudioContext = window.AudioContext || window.webkitAudioContext;
+                return AudioContext ? new AudioContext() : null;
+            };
+            let available: bool = js! { return @{&created} !== null; }.try_into().unwrap();
+            if available {
+                *context = Some(created);
+            }
+        }
+        context.clone()
+    }
+}
@@ -1,22 +1,37 @@
 use stdweb::web::html_element::CanvasElement;
-use stdweb::web::CanvasRenderingContext2d;
+use stdweb::web::{CanvasRenderingContext2d, FillRule};
 use graphics_data::GraphicsData;
-use graphics_data::PixelData;
+use graphics_data::{PixelData, Paint};
+use graphics_data::HexLayout;
+use grid::Grid;
+use snake::MoveDirection;
 use js_utils::get_date;
 use chrono::{DateTime, FixedOffset};
 use time::Duration;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use theme::Theme;
+use sprites::{SnakeStyle, SpriteAtlas};
+
+// how see-through the ghost snake is, 0 is invisible
+const GHOST_ALPHA: f64 = 0.35;
 
 // struct representing the canvas and display logic
 pub struct Canvas {
     canvas_dom_element: CanvasElement,
     ctx: CanvasRenderingContext2d,
-    background_color: String,
+    // shared with the options panel so a new theme shows up straight away
+    theme: Rc<RefCell<Theme>>,
+    // these too, a new style shows up on the next frame
+    style: Rc<Cell<SnakeStyle>>,
+    atlas: Rc<SpriteAtlas>,
     duration_between_frames: Duration,
     last_frame: DateTime<FixedOffset>
 }
 
 impl Canvas {
-    pub fn new(e: CanvasElement, frame_rate: u32) -> Result<Self, String> {
+    pub fn new(e: CanvasElement, frame_rate: u32, theme: Rc<RefCell<Theme>>,
+               style: Rc<Cell<SnakeStyle>>, atlas: Rc<SpriteAtlas>) -> Result<Self, String> {
         let ctx: CanvasRenderingContext2d = match e.get_context() {
             Ok(ctx) => ctx,
             Err(e) => {
@@ -26,7 +41,9 @@ impl Canvas {
         Ok(Canvas {
             canvas_dom_element: e,
             ctx,
-            background_color: String::from("white"),
+            theme,
+            style,
+            atlas,
             duration_between_frames: Duration::milliseconds(((1.0 / frame_rate as f64) * 1000.0) as i64),
             last_frame: get_date()
         })
@@ -41,11 +58,49 @@ impl Canvas {
             self.clear_screen();
             let pixel_width = self.canvas_dom_element.width() as f64 / data.width as f64;
             let pixel_height = self.canvas_dom_element.height() as f64 / data.height as f64;
-            for pixel in data.pixels.iter() {
-                self.draw_pixel(pixel, pixel_width, pixel_height);
+            match data.grid {
+                Grid::Square | Grid::EightWay => {
+                    self.ctx.set_global_alpha(GHOST_ALPHA);
+                    for pixel in data.ghost_pixels.iter() {
+                        self.draw_pixel(pixel, pixel_width, pixel_height);
+                    }
+                    self.ctx.set_global_alpha(1.0);
+                    // the flat squares stand in for the sprites until the atlas has loaded
+                    let sprites = data.grid == Grid::Square && self.style.get() == SnakeStyle::Sprites && self.atlas.ready();
+                    for pixel in data.pixels.iter() {
+                        if !(sprites && pixel.paint().is_snake()) {
+                            self.draw_pixel(pixel, pixel_width, pixel_height);
+                        }
+                    }
+                    self.draw_grid(pixel_width, pixel_height);
+                    if sprites {
+                        for sprite in data.sprites.iter() {
+                            self.atlas.draw(&self.ctx, sprite, pixel_width, pixel_height);
+                        }
+                    }
+                    if data.grid == Grid::Square {
+                        self.draw_x();
+                    } else {
+                        self.draw_zones(&[22.5, 67.5, 112.5, 157.5]);
+                    }
+                }
+                Grid::Hex => {
+                    let layout = HexLayout::new(self.canvas_dom_element.width() as f64,
+                                                self.canvas_dom_element.height() as f64,
+                                                data.width,
+                                                data.height);
+                    self.ctx.set_global_alpha(GHOST_ALPHA);
+                    for pixel in data.ghost_pixels.iter() {
+                        self.draw_hex_pixel(pixel, &layout);
+                    }
+                    self.ctx.set_global_alpha(1.0);
+                    for pixel in data.pixels.iter() {
+                        self.draw_hex_pixel(pixel, &layout);
+                    }
+                    self.draw_hex_grid(&layout, data.width, data.height);
+                    self.draw_zones(&[30.0, 90.0, 150.0]);
+                }
             }
-            self.draw_grid(pixel_width, pixel_height);
-            self.draw_x();
         }
         time_diff = get_date() - self.last_frame;
         time_diff = self.duration_between_frames - time_diff;
@@ -55,16 +110,141 @@ impl Canvas {
         time_diff.num_milliseconds()
     }
 
+    fn color(&self, paint: Paint) -> String {
+        let theme = self.theme.borrow();
+        match paint {
+            Paint::Apple => theme.apple.clone(),
+            Paint::Wall => theme.walls.clone(),
+            Paint::Portal(i) => theme.portal(i),
+            Paint::Head(_) => theme.head.clone(),
+            Paint::Body => theme.body.clone(),
+            Paint::Tail => theme.tail.clone(),
+        }
+    }
+
     fn draw_pixel(&self, pixel: &PixelData, pixel_width: f64, pixel_height: f64) {
         match *pixel {
-            PixelData::Pixel{x, y, ref color} => {
-                self.ctx.set_fill_style_color(color.as_ref());
-                self.ctx.fill_rect(pixel_width * x as f64, pixel_height * y as f64, pixel_width, pixel_height);
+            PixelData::Pixel{x, y, paint} => {
+                self.fill_cell(paint, (pixel_width * x as f64, pixel_height * y as f64), (pixel_width, pixel_height));
+            },
+            PixelData::SubPixel {x, y, c1, c2, paint} => {
+                self.fill_cell(paint,
+                               ((c1.0 + x as f64) * pixel_width, (c1.1 + y as f64) * pixel_height),
+                               (c2.0 * pixel_width, c2.1 * pixel_height));
+            },
+            PixelData::Between {from_x, from_y, x, y, progress, paint} => {
+                let left = from_x as f64 + (x as f64 - from_x as f64) * progress;
+                let top = from_y as f64 + (y as f64 - from_y as f64) * progress;
+                self.fill_cell(paint, (left * pixel_width, top * pixel_height), (pixel_width, pixel_height));
+            },
+            PixelData::Segment {from, to, paint} => {
+                self.ctx.set_stroke_style_color(&self.color(paint));
+                self.ctx.set_line_width(pixel_width.min(pixel_height));
+                self.ctx.begin_path();
+                self.ctx.move_to((from.0 + 0.5) * pixel_width, (from.1 + 0.5) * pixel_height);
+                self.ctx.line_to((to.0 + 0.5) * pixel_width, (to.1 + 0.5) * pixel_height);
+                self.ctx.stroke();
+            },
+        }
+    }
+
+    // hexes don't come in pieces, so sub pixels get drawn as the whole cell
+    fn draw_hex_pixel(&self, pixel: &PixelData, layout: &HexLayout) {
+        let (center, paint) = match *pixel {
+            PixelData::Pixel {x, y, paint} | PixelData::SubPixel {x, y, paint, ..} => {
+                (layout.center(x as i32, y as i32), paint)
             },
-            PixelData::SubPixel {x, y, c1, c2, ref color} => {
-                self.ctx.set_fill_style_color(color.as_ref());
-                self.ctx.fill_rect((c1.0 + x as f64) * pixel_width, (c1.1 + y as f64) * pixel_height, c2.0 * pixel_width, c2.1 * pixel_height);
+            PixelData::Between {from_x, from_y, x, y, progress, paint} => {
+                let from = layout.center(from_x, from_y);
+                let to = layout.center(x as i32, y as i32);
+                ((from.0 + (to.0 - from.0) * progress, from.1 + (to.1 - from.1) * progress), paint)
             },
+            // hexes already share an edge with every neighbor, there are no corners to bridge
+            PixelData::Segment {..} => return,
+        };
+        self.ctx.set_fill_style_color(&self.color(paint));
+        if paint == Paint::Apple {
+            self.fill_circle(center, layout.cell_width * 0.45);
+        } else {
+            self.hex_path(layout, center);
+            self.ctx.fill(FillRule::NonZero);
+        }
+        if let Paint::Head(direction) = paint {
+            self.draw_eyes(center, layout.cell_width, direction);
+        }
+    }
+
+    // apples come out round and heads get eyes, so telling them apart doesn't rely on color
+    fn fill_cell(&self, paint: Paint, (left, top): (f64, f64), (width, height): (f64, f64)) {
+        let center = (left + width / 2.0, top + height / 2.0);
+        self.ctx.set_fill_style_color(&self.color(paint));
+        if paint == Paint::Apple {
+            self.fill_circle(center, width.min(height) * 0.45);
+        } else {
+            self.ctx.fill_rect(left, top, width, height);
+        }
+        if let Paint::Head(direction) = paint {
+            self.draw_eyes(center, width.min(height), direction);
+        }
+    }
+
+    fn fill_circle(&self, (x, y): (f64, f64), radius: f64) {
+        self.ctx.begin_path();
+        self.ctx.arc(x, y, radius, 0.0, 2.0 * ::std::f64::consts::PI, false);
+        self.ctx.fill(FillRule::NonZero);
+    }
+
+    // a pair of eyes a little way towards the front of a head `size` across
+    fn draw_eyes(&self, (x, y): (f64, f64), size: f64, direction: MoveDirection) {
+        let (dx, dy) = direction.offset();
+        let length = ((dx * dx + dy * dy) as f64).sqrt();
+        let (forward_x, forward_y) = (dx as f64 / length, dy as f64 / length);
+        self.ctx.set_fill_style_color(&self.theme.borrow().background);
+        for side in [-1.0, 1.0].iter() {
+            self.fill_circle((x + forward_x * size * 0.18 - forward_y * size * 0.22 * side,
+                              y + forward_y * size * 0.18 + forward_x * size * 0.22 * side),
+                             size * 0.1);
+        }
+    }
+
+    fn hex_path(&self, layout: &HexLayout, (x, y): (f64, f64)) {
+        let (half_width, r) = (layout.cell_width / 2.0, layout.radius);
+        self.ctx.begin_path();
+        self.ctx.move_to(x, y - r);
+        self.ctx.line_to(x + half_width, y - r / 2.0);
+        self.ctx.line_to(x + half_width, y + r / 2.0);
+        self.ctx.line_to(x, y + r);
+        self.ctx.line_to(x - half_width, y + r / 2.0);
+        self.ctx.line_to(x - half_width, y - r / 2.0);
+        self.ctx.close_path();
+    }
+
+    fn draw_hex_grid(&self, layout: &HexLayout, width: u32, height: u32) {
+        self.ctx.set_stroke_style_color(&self.theme.borrow().grid);
+        self.ctx.set_line_width(1.0);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                self.hex_path(layout, layout.center(x, y));
+                self.ctx.stroke();
+            }
+        }
+    }
+
+    // the touch zones for grids with more than four directions all meet in the
+    // middle, split by a line through it at each of `angles` degrees
+    fn draw_zones(&self, angles: &[f64]) {
+        let w = self.canvas_dom_element.width() as f64;
+        let h = self.canvas_dom_element.height() as f64;
+        let length = w + h;
+
+        self.ctx.set_stroke_style_color(&self.theme.borrow().ui);
+        self.ctx.set_line_width(2.0);
+        for angle in angles.iter() {
+            let (dx, dy) = (angle.to_radians().cos() * length, angle.to_radians().sin() * length);
+            self.ctx.begin_path();
+            self.ctx.move_to(w / 2.0 - dx, h / 2.0 - dy);
+            self.ctx.line_to(w / 2.0 + dx, h / 2.0 + dy);
+            self.ctx.stroke();
         }
     }
 
@@ -73,7 +253,7 @@ impl Canvas {
         let h = self.canvas_dom_element.height() as f64;
 
         // line from top left to bottom right corner
-        self.ctx.set_stroke_style_color("black");
+        self.ctx.set_stroke_style_color(&self.theme.borrow().ui);
         self.ctx.set_line_width(2.0);
         self.ctx.begin_path();
         self.ctx.move_to(0.0, 0.0);
@@ -95,7 +275,7 @@ impl Canvas {
         // horizontal lines
         let mut curr_h = h;
         while curr_h > 0.0 {
-            self.ctx.set_stroke_style_color("grey");
+            self.ctx.set_stroke_style_color(&self.theme.borrow().grid);
             self.ctx.set_line_width(1.0);
             self.ctx.begin_path();
             self.ctx.move_to(0.0, curr_h);
@@ -107,7 +287,7 @@ impl Canvas {
         // vertical lines
         let mut curr_w = w;
         while curr_w > 0.0 {
-            self.ctx.set_stroke_style_color("grey");
+            self.ctx.set_stroke_style_color(&self.theme.borrow().grid);
             self.ctx.set_line_width(1.0);
             self.ctx.begin_path();
             self.ctx.move_to(curr_w, 0.0);
@@ -118,7 +298,7 @@ impl Canvas {
     }
 
     fn clear_screen(&self) {
-        self.ctx.set_fill_style_color(&self.background_color);
+        self.ctx.set_fill_style_color(&self.theme.borrow().background);
         self.ctx.fill_rect(0.0,
                            0.0,
                            self.canvas_dom_element.width() as f64,
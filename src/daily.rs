@@ -0,0 +1,56 @@
+use chrono::NaiveDate;
+use snake::{GameResult, GameSettings};
+use game_mode::GameMode;
+use js_utils::get_date;
+use rng::Rng;
+use storage;
+
+const DAILY_RESULTS_KEY: &str = "daily-results";
+
+#[derive(Serialize, Deserialize)]
+pub struct DailyResult {
+    pub date: NaiveDate,
+    pub result: GameResult,
+}
+
+// everyone should get the same challenge no matter their timezone, so the
+// day rolls over at midnight utc
+pub fn today() -> NaiveDate {
+    get_date().naive_utc().date()
+}
+
+// board size, speed, and apple sequence all come from the date
+pub fn settings_for(date: NaiveDate) -> GameSettings {
+    let mut rng = Rng::new(date_seed(date));
+    GameSettings {
+        width: 8 + rng.below(9),
+        height: 6 + rng.below(7),
+        frame_rate: 4 + rng.below(5),
+        mode: GameMode::Classic,
+        seed: rng.next_u64(),
+        ..GameSettings::default()
+    }
+}
+
+pub fn load_results() -> Vec<DailyResult> {
+    storage::load(DAILY_RESULTS_KEY).unwrap_or_else(Vec::new)
+}
+
+pub fn record_result(date: NaiveDate, result: GameResult) -> Result<(), String> {
+    let mut results = load_results();
+    results.push(DailyResult {
+        date,
+        result,
+    });
+    storage::save(DAILY_RESULTS_KEY, &results)
+}
+
+// fnv-1a, std's hashers aren't guaranteed to be stable between releases
+fn date_seed(date: NaiveDate) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in date.format("%Y-%m-%d").to_string().bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,70 @@
+use std::cell::RefCell;
+use snake::MoveDirection;
+
+// what the snake ran into
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum DeathCause {
+    Wall,
+    Itself,
+    Hazard,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Item {
+    Apple,
+    Hazard,
+}
+
+// something that happened during a turn of the game
+#[derive(Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    AppleEaten {
+        apples_eaten: u32,
+    },
+    Turned {
+        from: MoveDirection,
+        to: MoveDirection,
+    },
+    // the snake got one square longer
+    Grew {
+        length: u32,
+    },
+    Died(DeathCause),
+    // carrying on the way the snake is heading would crash it next turn
+    DangerAhead,
+    SpeedChanged {
+        // game frames per second
+        frame_rate: f64,
+    },
+    ItemSpawned {
+        item: Item,
+        x: i32,
+        y: i32,
+    },
+}
+
+// hands game events out to whoever is interested in them
+pub struct EventBus {
+    subscribers: RefCell<Vec<Box<dyn Fn(&GameEvent)>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus {
+            subscribers: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn subscribe<F>(&self, f: F) where F: Fn(&GameEvent) + 'static {
+        self.subscribers.borrow_mut().push(Box::new(f));
+    }
+
+    pub fn publish(&self, events: &[GameEvent]) {
+        let subscribers = self.subscribers.borrow();
+        for event in events.iter() {
+            for subscriber in subscribers.iter() {
+                subscriber(event);
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::Duration as StdDuration;
+use puzzle::PuzzleLimit;
+
+// how long a time attack game lasts unless told otherwise
+pub const DEFAULT_TIME_ATTACK_SECONDS: u32 = 60;
+// number of turns between each new hazard in survival mode
+const SURVIVAL_HAZARD_INTERVAL: u32 = 20;
+// number of apples between each speed up in survival mode
+const SURVIVAL_SPEED_UP_INTERVAL: u32 = 5;
+
+// the rules a game is played by
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GameMode {
+    // play until you run into something
+    Classic,
+    // eat as many apples as possible before the clock runs out
+    TimeAttack {
+        seconds: u32,
+    },
+    // hazards keep appearing on the board, score is how long you last
+    Survival,
+    // nothing kills you, the walls wrap around
+    Zen,
+    // eat a fixed sequence of apples before running out of moves or time
+    Puzzle {
+        limit: PuzzleLimit,
+    },
+}
+
+impl GameMode {
+    // parse the value of one of the options in the game mode picker
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        match name {
+            "classic" => Some(GameMode::Classic),
+            "time-attack" => Some(GameMode::TimeAttack { seconds: DEFAULT_TIME_ATTACK_SECONDS }),
+            "survival" => Some(GameMode::Survival),
+            "zen" => Some(GameMode::Zen),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::TimeAttack { .. } => "time-attack",
+            GameMode::Survival => "survival",
+            GameMode::Zen => "zen",
+            GameMode::Puzzle { .. } => "puzzle",
+        }
+    }
+
+    // how much game time may pass before the game ends
+    pub fn time_limit(self) -> Option<StdDuration> {
+        match self {
+            GameMode::TimeAttack { seconds } => Some(StdDuration::from_secs(seconds as u64)),
+            GameMode::Puzzle { limit: PuzzleLimit::Seconds(seconds) } => Some(StdDuration::from_secs(seconds as u64)),
+            _ => None,
+        }
+    }
+
+    // how many turns may be taken before the game ends
+    pub fn move_limit(self) -> Option<u32> {
+        match self {
+            GameMode::Puzzle { limit: PuzzleLimit::Moves(moves) } => Some(moves),
+            _ => None,
+        }
+    }
+
+    // whether running into a wall, hazard, or yourself ends the game
+    pub fn collisions_kill(self) -> bool {
+        self != GameMode::Zen
+    }
+
+    // whether leaving one side of the board puts you on the other side
+    pub fn wraps_edges(self) -> bool {
+        self == GameMode::Zen
+    }
+
+    // number of turns between new hazards, if this mode has any
+    pub fn hazard_interval(self) -> Option<u32> {
+        match self {
+            GameMode::Survival => Some(SURVIVAL_HAZARD_INTERVAL),
+            _ => None,
+        }
+    }
+
+    // number of apples between each time the snake speeds up, if it ever does
+    pub fn speed_up_interval(self) -> Option<u32> {
+        match self {
+            GameMode::Survival => Some(SURVIVAL_SPEED_UP_INTERVAL),
+            _ => None,
+        }
+    }
+
+    pub fn score(self, apples_eaten: u32, turns_passed: u32) -> u32 {
+        match self {
+            GameMode::Survival => turns_passed,
+            _ => apples_eaten,
+        }
+    }
+}
+
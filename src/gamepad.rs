@@ -0,0 +1,122 @@
+use serde_json;
+use stdweb::unstable::TryInto;
+use grid::Grid;
+use keybindings::{Action, Input};
+use snake::MoveDirection;
+
+// how far the left stick has to be pushed before it counts, from 0 to 1
+const DEAD_ZONE: f64 = 0.5;
+
+// button numbers in the browser's standard controller layout
+const DPAD_UP: usize = 12;
+const DPAD_DOWN: usize = 13;
+const DPAD_LEFT: usize = 14;
+const DPAD_RIGHT: usize = 15;
+
+// a/cross, b/circle, the shoulder buttons, back/select and start
+const BUTTONS: [(usize, Action); 6] = [
+    (0, Action::Boost),
+    (1, Action::Rewind),
+    (4, Action::TurnLeft),
+    (5, Action::TurnRight),
+    (8, Action::Restart),
+    (9, Action::Pause),
+];
+
+// what the first connected controller looks like right now
+#[derive(Deserialize)]
+struct PadState {
+    buttons: Vec<bool>,
+    axes: Vec<f64>,
+}
+
+impl PadState {
+    fn held(&self, button: usize) -> bool {
+        self.buttons.get(button).cloned().unwrap_or(false)
+    }
+}
+
+// the browser only hands out controller state when asked, so this gets
+// asked every frame and turns whatever changed since last time into inputs
+pub struct Gamepad {
+    direction: Option<MoveDirection>,
+    held: Vec<Action>,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        Gamepad {
+            direction: None,
+            held: Vec::new(),
+        }
+    }
+
+    pub fn poll(&mut self, grid: Grid) -> Vec<Input> {
+        let json: Option<String> = js! {
+            var pads = navigator.getGamepads ? navigator.getGamepads() : [];
+            for (var i = 0; i < pads.length; i++) {
+                var pad = pads[i];
+                if (pad && pad.connected) {
+                    return JSON.stringify({
+                        buttons: Array.prototype.map.call(pad.buttons, function(b) { return b.pressed; }),
+                        axes: Array.prototype.slice.call(pad.axes),
+                    });
+                }
+            }
+            return null;
+        }.try_into().unwrap_or(None);
+        let state: Option<PadState> = json.and_then(|j| serde_json::from_str(&j).ok());
+
+        let mut inputs = Vec::new();
+        let state = match state {
+            Some(state) => state,
+            None => {
+                // a controller unplugged mid boost shouldn't leave the snake boosting
+                self.direction = None;
+                for action in self.held.drain(..) {
+                    inputs.push(Input::Released(action));
+                }
+                return inputs;
+            }
+        };
+
+        for &(button, action) in BUTTONS.iter() {
+            let was_held = self.held.contains(&action);
+            if state.held(button) && !was_held {
+                self.held.push(action);
+                inputs.push(Input::Pressed(action));
+            } else if !state.held(button) && was_held {
+                self.held.retain(|a| *a != action);
+                inputs.push(Input::Released(action));
+            }
+        }
+
+        // the d-pad wins over the stick, and holding two d-pad buttons goes diagonally
+        let mut dx = 0.0;
+        let mut dy = 0.0;
+        if state.held(DPAD_UP) { dy -= 1.0; }
+        if state.held(DPAD_DOWN) { dy += 1.0; }
+        if state.held(DPAD_LEFT) { dx -= 1.0; }
+        if state.held(DPAD_RIGHT) { dx += 1.0; }
+        if dx == 0.0 && dy == 0.0 && state.axes.len() >= 2 {
+            let (x, y) = (state.axes[0], state.axes[1]);
+            if x.hypot(y) > DEAD_ZONE {
+                dx = x;
+                dy = y;
+            }
+        }
+        let direction = if dx == 0.0 && dy == 0.0 {
+            None
+        } else {
+            Some(grid.direction_towards(dx, dy))
+        };
+        // holding a direction only steers once, like holding a key down doesn't fill the buffer
+        if direction != self.direction {
+            if let Some(d) = direction {
+                inputs.push(Input::Pressed(Action::steer(d)));
+            }
+            self.direction = direction;
+        }
+        inputs
+    }
+}
@@ -1,11 +1,37 @@
 
+use grid::Grid;
+use snake::MoveDirection;
+
+// what something on the board is, the canvas picks a color for it from the theme
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Paint {
+    Apple,
+    // hazards, anything that hurts to run into
+    Wall,
+    // which pair, each pair gets its own color so it's clear where they lead
+    Portal(usize),
+    // which way it's facing
+    Head(MoveDirection),
+    Body,
+    Tail,
+}
+
+impl Paint {
+    pub fn is_snake(self) -> bool {
+        match self {
+            Paint::Head(_) | Paint::Body | Paint::Tail => true,
+            Paint::Apple | Paint::Wall | Paint::Portal(_) => false,
+        }
+    }
+}
+
 // possible graphics data types
 pub enum PixelData {
     // a full pixel at position (x, y)
     Pixel {
         x: u32,
         y: u32,
-        color: String
+        paint: Paint
     },
     // a partial pixel at position (x, y)
     //
@@ -31,41 +57,157 @@ pub enum PixelData {
         y: u32,
         c1: (f64, f64),
         c2: (f64, f64),
-        color: String
+        paint: Paint
+    },
+    // a whole pixel `progress` of the way from (from_x, from_y) to (x, y),
+    // for grids whose cells don't split up into rectangles
+    //
+    // the pixel it's coming from can be just off the edge of the screen
+    Between {
+        from_x: i32,
+        from_y: i32,
+        x: u32,
+        y: u32,
+        progress: f64,
+        paint: Paint
+    },
+    // a band one pixel wide running from the middle of the pixel at `from` to
+    // the middle of the pixel at `to`, which don't have to be whole positions
+    Segment {
+        from: (f64, f64),
+        to: (f64, f64),
+        paint: Paint
+    }
+}
+
+impl PixelData {
+    pub fn paint(&self) -> Paint {
+        match *self {
+            PixelData::Pixel { paint, .. } => paint,
+            PixelData::SubPixel { paint, .. } => paint,
+            PixelData::Between { paint, .. } => paint,
+            PixelData::Segment { paint, .. } => paint,
+        }
+    }
+}
+
+// a piece of the snake that knows which way it's going, for drawing the
+// snake out of pictures instead of flat squares
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Sprite {
+    // facing the way it's heading
+    Head(MoveDirection),
+    // the way the snake was going when it came into the square and the way it
+    // goes out, a straight piece when they're the same and a corner otherwise
+    Body {
+        from: MoveDirection,
+        to: MoveDirection,
+    },
+    // heading the way the snake moves, the thin end trails behind
+    Tail(MoveDirection),
+}
+
+// a sprite with its top left corner at (x, y), which don't have to be
+// whole positions so the ends can slide between squares
+pub struct SpriteData {
+    pub x: f64,
+    pub y: f64,
+    pub sprite: Sprite,
+}
+
+// where hex cells go on the canvas
+//
+// every odd row sticks out half a cell to the right, and each row
+// tucks a quarter of the way up into the one above it
+pub struct HexLayout {
+    pub cell_width: f64,
+    pub row_height: f64,
+    // from the middle of a hex to its top point
+    pub radius: f64,
+}
+
+impl HexLayout {
+    pub fn new(canvas_width: f64, canvas_height: f64, width: u32, height: u32) -> Self {
+        let row_height = canvas_height / (height as f64 + 1.0 / 3.0);
+        HexLayout {
+            cell_width: canvas_width / (width as f64 + 0.5),
+            row_height,
+            radius: row_height * 2.0 / 3.0,
+        }
+    }
+
+    pub fn center(&self, x: i32, y: i32) -> (f64, f64) {
+        let shift = if y & 1 == 1 { 0.5 } else { 0.0 };
+        ((x as f64 + 0.5 + shift) * self.cell_width, self.radius + y as f64 * self.row_height)
     }
 }
 
 // collection of graphics data to reunder
 pub struct GraphicsData {
     pub pixels: Vec<PixelData>,
+    // drawn see-through underneath everything else
+    pub ghost_pixels: Vec<PixelData>,
+    // the snake again as sprites, only filled in on square grids
+    pub sprites: Vec<SpriteData>,
     pub width: u32,
-    pub height: u32
+    pub height: u32,
+    pub grid: Grid
 }
 
 impl GraphicsData {
-    pub fn new(width: u32, height: u32) -> Self {
+    pub fn new(width: u32, height: u32, grid: Grid) -> Self {
         GraphicsData {
             pixels: Vec::new(),
+            ghost_pixels: Vec::new(),
+            sprites: Vec::new(),
             width,
-            height
+            height,
+            grid
         }
     }
 
-    pub fn add_pixel(&mut self, x: u32, y: u32, color: String) {
+    pub fn add_pixel(&mut self, x: u32, y: u32, paint: Paint) {
         self.pixels.push(PixelData::Pixel {
             x,
             y,
-            color,
+            paint,
         })
     }
 
-    pub fn add_sub_pixel(&mut self, x: u32, y: u32, c1: (f64, f64), c2: (f64, f64), color: String) {
+    pub fn add_sub_pixel(&mut self, x: u32, y: u32, c1: (f64, f64), c2: (f64, f64), paint: Paint) {
         self.pixels.push( PixelData::SubPixel {
             x,
             y,
             c1,
             c2,
-            color,
+            paint,
+        })
+    }
+
+    pub fn add_between(&mut self, from_x: i32, from_y: i32, x: u32, y: u32, progress: f64, paint: Paint) {
+        self.pixels.push(PixelData::Between {
+            from_x,
+            from_y,
+            x,
+            y,
+            progress,
+            paint,
+        })
+    }
+
+    pub fn add_sprite(&mut self, x: f64, y: f64, sprite: Sprite) {
+        self.sprites.push(SpriteData {
+            x,
+            y,
+            sprite,
+        })
+    }
+
+    pub fn add_segment(&mut self, from: (f64, f64), to: (f64, f64), paint: Paint) {
+        self.pixels.push(PixelData::Segment {
+            from,
+            to,
+            paint,
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,213 @@
+use snake::{MoveDirection, Rotation};
+
+const SQUARE_DIRECTIONS: [MoveDirection; 4] = [
+    MoveDirection::Up,
+    MoveDirection::Down,
+    MoveDirection::Left,
+    MoveDirection::Right,
+];
+
+const EIGHT_WAY_DIRECTIONS: [MoveDirection; 8] = [
+    MoveDirection::Up,
+    MoveDirection::Down,
+    MoveDirection::Left,
+    MoveDirection::Right,
+    MoveDirection::UpLeft,
+    MoveDirection::UpRight,
+    MoveDirection::DownLeft,
+    MoveDirection::DownRight,
+];
+
+// going clockwise round from the right
+const HEX_CLOCKWISE: [MoveDirection; 6] = [
+    MoveDirection::Right,
+    MoveDirection::DownRight,
+    MoveDirection::DownLeft,
+    MoveDirection::Left,
+    MoveDirection::UpLeft,
+    MoveDirection::UpRight,
+];
+
+const HEX_DIRECTIONS: [MoveDirection; 6] = [
+    MoveDirection::Left,
+    MoveDirection::Right,
+    MoveDirection::UpLeft,
+    MoveDirection::UpRight,
+    MoveDirection::DownLeft,
+    MoveDirection::DownRight,
+];
+
+// the shape of the board's cells, which decides where the snake can go from each one
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Grid {
+    Square,
+    // square cells, but the snake can cut across the corners too
+    //
+    // slipping diagonally between two pieces of the snake that are themselves
+    // joined diagonally counts as running into yourself, see `crosses_diagonal`
+    EightWay,
+    // pointy topped hexagons in rows, with every odd row pushed half a cell to the right
+    Hex,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid::Square
+    }
+}
+
+impl Grid {
+    pub fn from_name(name: &str) -> Option<Grid> {
+        match name {
+            "square" => Some(Grid::Square),
+            "eight-way" => Some(Grid::EightWay),
+            "hex" => Some(Grid::Hex),
+            _ => None,
+        }
+    }
+
+    pub fn directions(self) -> &'static [MoveDirection] {
+        match self {
+            Grid::Square => &SQUARE_DIRECTIONS,
+            Grid::EightWay => &EIGHT_WAY_DIRECTIONS,
+            Grid::Hex => &HEX_DIRECTIONS,
+        }
+    }
+
+    pub fn allows(self, direction: MoveDirection) -> bool {
+        self.directions().contains(&direction)
+    }
+
+    // the cell next to (x, y) in the given direction, which may be off the board
+    pub fn step(self, (x, y): (i32, i32), direction: MoveDirection) -> (i32, i32) {
+        match self {
+            Grid::Square | Grid::EightWay => {
+                let (dx, dy) = direction.offset();
+                (x + dx, y + dy)
+            }
+            Grid::Hex => {
+                // going up or down a row lands half a cell to one side, which side
+                // depends on whether this row is one of the pushed over ones
+                let shift = if y & 1 == 1 { 1 } else { 0 };
+                match direction {
+                    MoveDirection::Left => (x - 1, y),
+                    MoveDirection::Right => (x + 1, y),
+                    MoveDirection::UpLeft => (x - 1 + shift, y - 1),
+                    MoveDirection::UpRight => (x + shift, y - 1),
+                    MoveDirection::DownLeft => (x - 1 + shift, y + 1),
+                    MoveDirection::DownRight => (x + shift, y + 1),
+                    // not a way you can go on a hex grid
+                    MoveDirection::Up | MoveDirection::Down => (x, y),
+                }
+            }
+        }
+    }
+
+    // the heading after turning the smallest amount this grid allows, a quarter
+    // turn on square boards and a sixth of one on hex boards
+    pub fn turn(self, direction: MoveDirection, rotation: Rotation) -> MoveDirection {
+        match self {
+            Grid::Square | Grid::EightWay => direction.rotated(rotation),
+            Grid::Hex => {
+                let i = HEX_CLOCKWISE.iter().position(|d| *d == direction).unwrap_or(0);
+                match rotation {
+                    Rotation::Clockwise => HEX_CLOCKWISE[(i + 1) % 6],
+                    Rotation::CounterClockwise => HEX_CLOCKWISE[(i + 5) % 6],
+                }
+            }
+        }
+    }
+
+    // the two cells a diagonal step on a square board squeezes between,
+    // which are (x, y) stepped along each of the step's axes separately
+    pub fn diagonal_corners(self, direction: MoveDirection) -> Option<(MoveDirection, MoveDirection)> {
+        if self == Grid::Hex {
+            return None;
+        }
+        match direction {
+            MoveDirection::UpLeft => Some((MoveDirection::Up, MoveDirection::Left)),
+            MoveDirection::UpRight => Some((MoveDirection::Up, MoveDirection::Right)),
+            MoveDirection::DownLeft => Some((MoveDirection::Down, MoveDirection::Left)),
+            MoveDirection::DownRight => Some((MoveDirection::Down, MoveDirection::Right)),
+            _ => None,
+        }
+    }
+
+    // the direction to move in to get from one cell to the one next to it
+    pub fn direction_between(self, from: (i32, i32), to: (i32, i32)) -> MoveDirection {
+        self.directions().iter()
+            .cloned()
+            .find(|d| self.step(from, *d) == to)
+            .unwrap_or(MoveDirection::Right)
+    }
+
+    // roughly which way a direction points on screen, in degrees clockwise from the right
+    fn angle(self, direction: MoveDirection) -> f64 {
+        let diagonal = match self {
+            Grid::Hex => 60.0,
+            Grid::Square | Grid::EightWay => 45.0,
+        };
+        match direction {
+            MoveDirection::Right => 0.0,
+            MoveDirection::DownRight => diagonal,
+            MoveDirection::Down => 90.0,
+            MoveDirection::DownLeft => 180.0 - diagonal,
+            MoveDirection::Left => 180.0,
+            MoveDirection::UpLeft => 180.0 + diagonal,
+            MoveDirection::Up => 270.0,
+            MoveDirection::UpRight => 360.0 - diagonal,
+        }
+    }
+
+    // the direction that points closest to (dx, dy), with y going down the screen
+    pub fn direction_towards(self, dx: f64, dy: f64) -> MoveDirection {
+        let mut best = self.directions()[0];
+        let mut best_dot = ::std::f64::NEG_INFINITY;
+        for direction in self.directions().iter() {
+            let angle = self.angle(*direction).to_radians();
+            let dot = dx * angle.cos() + dy * angle.sin();
+            if dot > best_dot {
+                best = *direction;
+                best_dot = dot;
+            }
+        }
+        best
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_steps_shift_with_the_row() {
+        // even rows go up and down to the left, odd rows to the right
+        assert!(Grid::Hex.step((3, 2), MoveDirection::UpLeft) == (2, 1));
+        assert!(Grid::Hex.step((3, 2), MoveDirection::UpRight) == (3, 1));
+        assert!(Grid::Hex.step((3, 3), MoveDirection::DownLeft) == (3, 4));
+        assert!(Grid::Hex.step((3, 3), MoveDirection::DownRight) == (4, 4));
+        assert!(Grid::Hex.step((3, 3), MoveDirection::Up) == (3, 3));
+    }
+
+    #[test]
+    fn hex_steps_come_back() {
+        for &y in [2, 3].iter() {
+            for &direction in Grid::Hex.directions().iter() {
+                let there = Grid::Hex.step((3, y), direction);
+                assert!(Grid::Hex.step(there, direction.reverse()) == (3, y));
+                assert!(Grid::Hex.direction_between((3, y), there) == direction);
+            }
+        }
+    }
+
+    #[test]
+    fn six_hex_turns_go_all_the_way_round() {
+        let mut direction = MoveDirection::Right;
+        for _ in 0..6 {
+            direction = Grid::Hex.turn(direction, Rotation::Clockwise);
+        }
+        assert!(direction == MoveDirection::Right);
+        assert!(Grid::Hex.turn(MoveDirection::Right, Rotation::Clockwise) == MoveDirection::DownRight);
+    }
+}
@@ -0,0 +1,135 @@
+use graphics_data::HexLayout;
+use grid::Grid;
+use snake::{MoveDirection, Rotation};
+use triangle::{Point, Triangle};
+
+// the board as it's laid out on the canvas, for working out which way a tap
+// or click on it means, everything is in canvas pixels from the top left
+pub struct Screen {
+    pub grid: Grid,
+    // the board's size in cells
+    pub columns: u32,
+    pub rows: u32,
+    // the canvas's size in pixels
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Screen {
+    // where the middle of a cell is drawn
+    pub fn cell_center(&self, (x, y): (i32, i32)) -> (f64, f64) {
+        match self.grid {
+            Grid::Hex => HexLayout::new(self.width, self.height, self.columns, self.rows).center(x, y),
+            Grid::Square | Grid::EightWay => {
+                let cell_width = self.width / self.columns as f64;
+                let cell_height = self.height / self.rows as f64;
+                ((x as f64 + 0.5) * cell_width, (y as f64 + 0.5) * cell_height)
+            }
+        }
+    }
+
+    // the fixed zones: a triangle from each side of a square board to the middle,
+    // or a slice around the middle for each direction on the other grids
+    pub fn zone_direction(&self, (x, y): (f64, f64)) -> Option<MoveDirection> {
+        if self.grid != Grid::Square {
+            return Some(self.grid.direction_towards(x - self.width / 2.0, y - self.height / 2.0));
+        }
+
+        let width = self.width as i32;
+        let height = self.height as i32;
+        let middle = Point::new(width / 2, height / 2);
+        let zones = [
+            (MoveDirection::Up, Point::new(0, 0), Point::new(width, 0)),
+            (MoveDirection::Down, Point::new(0, height), Point::new(width, height)),
+            (MoveDirection::Left, Point::new(0, 0), Point::new(0, height)),
+            (MoveDirection::Right, Point::new(width, 0), Point::new(width, height)),
+        ];
+        let point = Point::new(x as i32, y as i32);
+        zones.iter()
+            .find(|&&(_, ref a, ref b)| Triangle::new(a.clone(), b.clone(), middle.clone()).contains(&point))
+            .map(|&(direction, _, _)| direction)
+    }
+
+    // turning relative to the snake's heading, by which half of the board was tapped
+    pub fn turn_for(&self, (x, _): (f64, f64)) -> Rotation {
+        if x < self.width / 2.0 {
+            Rotation::CounterClockwise
+        } else {
+            Rotation::Clockwise
+        }
+    }
+
+    // whichever way points from the middle of the head towards the tap,
+    // nothing if the tap is right on top of it
+    pub fn towards_head(&self, head: (i32, i32), (x, y): (f64, f64)) -> Option<MoveDirection> {
+        let (head_x, head_y) = self.cell_center(head);
+        let (dx, dy) = (x - head_x, y - head_y);
+        if dx == 0.0 && dy == 0.0 {
+            return None;
+        }
+        Some(self.grid.direction_towards(dx, dy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Screen {
+        Screen { grid: Grid::Square, columns: 10, rows: 10, width: 200.0, height: 100.0 }
+    }
+
+    fn hex() -> Screen {
+        Screen { grid: Grid::Hex, columns: 10, rows: 10, width: 200.0, height: 200.0 }
+    }
+
+    #[test]
+    fn zones_point_at_the_side_that_was_tapped() {
+        let screen = square();
+        assert!(screen.zone_direction((100.0, 5.0)) == Some(MoveDirection::Up));
+        assert!(screen.zone_direction((100.0, 95.0)) == Some(MoveDirection::Down));
+        assert!(screen.zone_direction((5.0, 50.0)) == Some(MoveDirection::Left));
+        assert!(screen.zone_direction((195.0, 50.0)) == Some(MoveDirection::Right));
+    }
+
+    #[test]
+    fn hex_zones_are_slices_around_the_middle() {
+        let screen = hex();
+        assert!(screen.zone_direction((190.0, 100.0)) == Some(MoveDirection::Right));
+        assert!(screen.zone_direction((10.0, 100.0)) == Some(MoveDirection::Left));
+        assert!(screen.zone_direction((60.0, 10.0)) == Some(MoveDirection::UpLeft));
+        assert!(screen.zone_direction((140.0, 190.0)) == Some(MoveDirection::DownRight));
+    }
+
+    #[test]
+    fn turns_go_by_half_of_the_board() {
+        let screen = square();
+        assert!(screen.turn_for((20.0, 50.0)) == Rotation::CounterClockwise);
+        assert!(screen.turn_for((180.0, 50.0)) == Rotation::Clockwise);
+    }
+
+    #[test]
+    fn square_cells_are_centered() {
+        assert!(square().cell_center((0, 0)) == (10.0, 5.0));
+        assert!(square().cell_center((9, 9)) == (190.0, 95.0));
+    }
+
+    #[test]
+    fn taps_steer_towards_the_head() {
+        let screen = square();
+        let head = (5, 5);
+        assert!(screen.towards_head(head, (110.0, 10.0)) == Some(MoveDirection::Up));
+        assert!(screen.towards_head(head, (10.0, 55.0)) == Some(MoveDirection::Left));
+        assert!(screen.towards_head(head, (110.0, 55.0)) == None);
+    }
+
+    #[test]
+    fn hex_taps_steer_towards_the_head() {
+        let screen = hex();
+        let head = (4, 4);
+        let (x, y) = screen.cell_center(head);
+        assert!(screen.towards_head(head, (x + 50.0, y)) == Some(MoveDirection::Right));
+        assert!(screen.towards_head(head, (x - 20.0, y - 40.0)) == Some(MoveDirection::UpLeft));
+        assert!(screen.towards_head(head, (x + 20.0, y + 40.0)) == Some(MoveDirection::DownRight));
+    }
+}
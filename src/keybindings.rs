@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+use snake::{MoveDirection, Rotation};
+use storage;
+
+const KEY_BINDINGS_KEY: &str = "key-bindings";
+
+// everything a key can be bound to
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    // a quarter turn relative to the snake's heading
+    TurnLeft,
+    TurnRight,
+    Rewind,
+    Pause,
+    Restart,
+    // speeds the game up for as long as it's held
+    Boost,
+    // stops the game where it is and scores it, zen games can't end any other way
+    EndGame,
+}
+
+pub const ALL_ACTIONS: [Action; 15] = [
+    Action::Up,
+    Action::Down,
+    Action::Left,
+    Action::Right,
+    Action::UpLeft,
+    Action::UpRight,
+    Action::DownLeft,
+    Action::DownRight,
+    Action::TurnLeft,
+    Action::TurnRight,
+    Action::Rewind,
+    Action::Pause,
+    Action::Restart,
+    Action::Boost,
+    Action::EndGame,
+];
+
+// an action starting or stopping, from a key or a controller button
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Input {
+    Pressed(Action),
+    Released(Action),
+}
+
+impl Action {
+    pub fn steer(direction: MoveDirection) -> Action {
+        match direction {
+            MoveDirection::Up => Action::Up,
+            MoveDirection::Down => Action::Down,
+            MoveDirection::Left => Action::Left,
+            MoveDirection::Right => Action::Right,
+            MoveDirection::UpLeft => Action::UpLeft,
+            MoveDirection::UpRight => Action::UpRight,
+            MoveDirection::DownLeft => Action::DownLeft,
+            MoveDirection::DownRight => Action::DownRight,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::UpLeft => "Up Left",
+            Action::UpRight => "Up Right",
+            Action::DownLeft => "Down Left",
+            Action::DownRight => "Down Right",
+            Action::TurnLeft => "Turn Left",
+            Action::TurnRight => "Turn Right",
+            Action::Rewind => "Rewind (practice)",
+            Action::Pause => "Pause",
+            Action::Restart => "Restart",
+            Action::Boost => "Boost (hold)",
+            Action::EndGame => "End Game",
+        }
+    }
+
+    pub fn rotation(self) -> Option<Rotation> {
+        match self {
+            Action::TurnLeft => Some(Rotation::CounterClockwise),
+            Action::TurnRight => Some(Rotation::Clockwise),
+            _ => None,
+        }
+    }
+
+    // pressing these once does the whole thing, so holding the key down shouldn't do it again
+    pub fn toggles(self) -> bool {
+        match self {
+            Action::Pause | Action::Restart | Action::EndGame => true,
+            _ => false,
+        }
+    }
+
+    pub fn direction(self) -> Option<MoveDirection> {
+        match self {
+            Action::Up => Some(MoveDirection::Up),
+            Action::Down => Some(MoveDirection::Down),
+            Action::Left => Some(MoveDirection::Left),
+            Action::Right => Some(MoveDirection::Right),
+            Action::UpLeft => Some(MoveDirection::UpLeft),
+            Action::UpRight => Some(MoveDirection::UpRight),
+            Action::DownLeft => Some(MoveDirection::DownLeft),
+            Action::DownRight => Some(MoveDirection::DownRight),
+            _ => None,
+        }
+    }
+
+    // steering keys only make sense as a cluster, so they go by where the key is on the
+    // keyboard (`KeyboardEvent.code`) and WASD stays WASD shaped on AZERTY or Dvorak
+    //
+    // the rest are mnemonics, P should pause whichever key has the P printed on it,
+    // so they go by the character the key types (`KeyboardEvent.key`)
+    fn by_position(self) -> bool {
+        self.direction().is_some() || self.rotation().is_some() || self == Action::Boost
+    }
+}
+
+// which keys do what, several keys to an action
+//
+// each key is stored as a code or a lowercase character, see `Action::by_position`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyBindings {
+    keys: BTreeMap<Action, Vec<String>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let defaults: [(Action, &[&str]); 15] = [
+            (Action::Up, &["KeyW", "ArrowUp"]),
+            (Action::Down, &["KeyS", "ArrowDown"]),
+            (Action::Left, &["KeyA", "ArrowLeft"]),
+            (Action::Right, &["KeyD", "ArrowRight"]),
+            (Action::UpLeft, &["KeyQ"]),
+            (Action::UpRight, &["KeyE"]),
+            (Action::DownLeft, &["KeyZ"]),
+            (Action::DownRight, &["KeyC"]),
+            (Action::TurnLeft, &["Comma"]),
+            (Action::TurnRight, &["Period"]),
+            (Action::Rewind, &["r"]),
+            (Action::Pause, &["p", "escape"]),
+            (Action::Restart, &["n"]),
+            (Action::Boost, &["Space"]),
+            (Action::EndGame, &["enter"]),
+        ];
+        let mut keys = BTreeMap::new();
+        for &(action, defaults) in defaults.iter() {
+            keys.insert(action, defaults.iter().map(|k| k.to_string()).collect());
+        }
+        KeyBindings { keys }
+    }
+}
+
+impl KeyBindings {
+    pub fn load() -> KeyBindings {
+        let mut bindings: KeyBindings = storage::load(KEY_BINDINGS_KEY).unwrap_or_default();
+        // actions added since the bindings were saved start out on their default keys
+        for (action, keys) in KeyBindings::default().keys.into_iter() {
+            bindings.keys.entry(action).or_insert(keys);
+        }
+        bindings
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        storage::save(KEY_BINDINGS_KEY, self)
+    }
+
+    pub fn keys(&self, action: Action) -> &[String] {
+        self.keys.get(&action).map(|k| k.as_ref()).unwrap_or(&[])
+    }
+
+    // what a key press with this code and key is bound to, if anything
+    pub fn action_for(&self, code: &str, key: &str) -> Option<Action> {
+        let key = key.to_lowercase();
+        ALL_ACTIONS.iter()
+            .cloned()
+            .find(|a| self.keys(*a).iter().any(|k| *k == if a.by_position() { code } else { key.as_ref() }))
+    }
+
+    // adds a key to an action, taking it away from whatever it did before
+    pub fn bind(&mut self, action: Action, code: &str, key: &str) {
+        let key = key.to_lowercase();
+        self.unbind(code, &key);
+        let stored = if action.by_position() { code.to_string() } else { key };
+        self.keys.entry(action).or_insert_with(Vec::new).push(stored);
+    }
+
+    // the keys for an action the way the page shows them, e.g. "P or Escape"
+    pub fn describe(&self, action: Action) -> String {
+        let labels: Vec<String> = self.keys(action).iter().map(|k| label(k)).collect();
+        if labels.is_empty() {
+            String::from("(no key)")
+        } else {
+            labels.join(" or ")
+        }
+    }
+
+    // the line of instructions along the top of the page, with whatever keys are bound now
+    pub fn help(&self) -> String {
+        let moves: Vec<String> = [Action::Up, Action::Left, Action::Down, Action::Right].iter()
+            .map(|&a| self.describe(a))
+            .collect();
+        format!("{}, or Touchscreen to Move! {} to Pause, {} to Start or Restart, hold {} to Boost, {} to End the Game. \
+                 {} and {} turn left and right, {} rewinds practice games. \
+                 Controllers work too (start pauses, back restarts, A boosts, B rewinds, shoulder buttons turn).",
+                moves.join(", "),
+                self.describe(Action::Pause),
+                self.describe(Action::Restart),
+                self.describe(Action::Boost),
+                self.describe(Action::EndGame),
+                self.describe(Action::TurnLeft),
+                self.describe(Action::TurnRight),
+                self.describe(Action::Rewind))
+    }
+
+    pub fn remove(&mut self, action: Action, key: &str) {
+        if let Some(keys) = self.keys.get_mut(&action) {
+            keys.retain(|k| k != key);
+        }
+    }
+
+    // the same press can match a code on one action and a character on another,
+    // so both have to go or `action_for` would only ever find one of them
+    fn unbind(&mut self, code: &str, key: &str) {
+        for (action, keys) in self.keys.iter_mut() {
+            let taken = if action.by_position() { code } else { key };
+            keys.retain(|k| k != taken);
+        }
+    }
+}
+
+// something readable for a stored key, "KeyW" is just W
+pub fn label(key: &str) -> String {
+    let key = if key.starts_with("Key") || key.starts_with("Digit") {
+        key.trim_start_matches("Key").trim_start_matches("Digit")
+    } else {
+        key
+    };
+    match key {
+        " " => String::from("Space"),
+        k if k.chars().count() == 1 => k.to_uppercase(),
+        k => {
+            // "escape" was stored lowercase, so give it its capital back
+            let mut chars = k.chars();
+            let first = chars.next().map(|c| c.to_uppercase().to_string()).unwrap_or_default();
+            first + chars.as_str()
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steering_goes_by_position_and_the_rest_by_letter() {
+        let keys = KeyBindings::default();
+        // w on an azerty keyboard is where z is on qwerty
+        assert!(keys.action_for("KeyW", "z") == Some(Action::Up));
+        assert!(keys.action_for("KeyN", "n") == Some(Action::Restart));
+        assert!(keys.action_for("KeyB", "N") == Some(Action::Restart));
+        assert!(keys.action_for("KeyX", "x") == None);
+    }
+
+    #[test]
+    fn binding_a_key_takes_it_from_its_old_action() {
+        let mut keys = KeyBindings::default();
+        keys.bind(Action::Left, "ArrowUp", "ArrowUp");
+        assert!(keys.action_for("ArrowUp", "ArrowUp") == Some(Action::Left));
+        assert!(!keys.keys(Action::Up).contains(&String::from("ArrowUp")));
+        keys.remove(Action::Left, "ArrowUp");
+        assert!(keys.action_for("ArrowUp", "ArrowUp") == None);
+    }
+
+    #[test]
+    fn binding_takes_keys_from_actions_stored_the_other_way() {
+        let mut keys = KeyBindings::default();
+        keys.bind(Action::Up, "KeyP", "p");
+        assert!(keys.action_for("KeyP", "p") == Some(Action::Up));
+        assert!(!keys.keys(Action::Pause).contains(&String::from("p")));
+
+        keys.bind(Action::Restart, "KeyD", "d");
+        assert!(keys.action_for("KeyD", "d") == Some(Action::Restart));
+        assert!(!keys.keys(Action::Right).contains(&String::from("KeyD")));
+    }
+
+    #[test]
+    fn help_shows_the_keys_bound_now() {
+        let mut keys = KeyBindings::default();
+        assert!(keys.help().contains("P or Escape to Pause"));
+        keys.remove(Action::Pause, "p");
+        keys.remove(Action::Pause, "escape");
+        assert!(keys.describe(Action::Pause) == "(no key)");
+        keys.bind(Action::Pause, "KeyK", "k");
+        assert!(keys.help().contains("K to Pause"));
+    }
+
+    #[test]
+    fn only_pause_and_restart_ignore_held_keys() {
+        assert!(Action::Pause.toggles());
+        assert!(Action::Restart.toggles());
+        assert!(!Action::Rewind.toggles());
+        assert!(!Action::Up.toggles());
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::NaiveDateTime;
+use snake::GameResult;
+
+// what the page sends the leaderboard server after a game
+#[derive(Serialize, Deserialize)]
+pub struct Submission {
+    pub name: String,
+    pub result: GameResult,
+}
+
+// what the server says back once a submission checks out
+#[derive(Serialize, Deserialize)]
+pub struct Accepted {
+    pub configuration: String,
+    // 1 is first place
+    pub rank: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LeaderboardScore {
+    pub name: String,
+    pub score: u32,
+    pub apples_eaten: u32,
+    pub turns_passed: u32,
+    // utc
+    pub date: NaiveDateTime,
+}
+
+// the top scores for one configuration, best first
+#[derive(Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub configuration: String,
+    pub scores: Vec<LeaderboardScore>,
+}
+
+// any request the server turns down
+#[derive(Serialize, Deserialize)]
+pub struct Rejected {
+    pub error: String,
+}
@@ -0,0 +1,73 @@
+use serde_json;
+use stdweb::Once;
+use storage;
+use snake::GameResult;
+use leaderboard::{Submission, Accepted, Leaderboard, Rejected};
+
+const LEADERBOARD_SETTINGS_KEY: &str = "leaderboard";
+
+// where to find the team's leaderboard server, nothing gets sent anywhere without a url
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LeaderboardSettings {
+    pub url: String,
+    pub name: String,
+}
+
+pub fn load_settings() -> LeaderboardSettings {
+    storage::load(LEADERBOARD_SETTINGS_KEY).unwrap_or_default()
+}
+
+pub fn save_settings(settings: &LeaderboardSettings) -> Result<(), String> {
+    storage::save(LEADERBOARD_SETTINGS_KEY, settings)
+}
+
+fn request<F>(method: &str, url: String, body: Option<String>, done: F)
+    where F: FnOnce(Result<String, String>) + 'static {
+    let done = Once(move |ok: bool, text: String| {
+        if ok {
+            done(Ok(text));
+        } else {
+            // the server explains itself in json, anything else is passed along as is
+            let error = serde_json::from_str::<Rejected>(&text).map(|r| r.error).unwrap_or(text);
+            done(Err(error));
+        }
+    });
+    js! {
+        var done = @{done};
+        var options = { method: @{method} };
+        var body = @{body};
+        if (body !== null) {
+            options.body = body;
+            options.headers = { "Content-Type": "application/json" };
+        }
+        fetch(@{url}, options).then(function(response) {
+            return response.text().then(function(text) {
+                done(response.ok, text);
+            });
+        }).catch(function(error) {
+            done(false, "couldn't reach the leaderboard server: " + error);
+        });
+    }
+}
+
+fn endpoint(settings: &LeaderboardSettings, path: &str) -> String {
+    format!("{}{}", settings.url.trim_end_matches('/'), path)
+}
+
+pub fn submit<F>(settings: &LeaderboardSettings, result: &GameResult, done: F)
+    where F: FnOnce(Result<Accepted, String>) + 'static {
+    let body = serde_json::to_string(&Submission {
+        name: settings.name.clone(),
+        result: result.clone(),
+    }).expect("failed to serialize leaderboard submission");
+    request("POST", endpoint(settings, "/scores"), Some(body), move |res| {
+        done(res.and_then(|text| serde_json::from_str(&text).map_err(|e| format!("unexpected reply from server: {}", e))));
+    });
+}
+
+pub fn fetch_leaderboards<F>(settings: &LeaderboardSettings, done: F)
+    where F: FnOnce(Result<Vec<Leaderboard>, String>) + 'static {
+    request("GET", endpoint(settings, "/leaderboards"), None, move |res| {
+        done(res.and_then(|text| serde_json::from_str(&text).map_err(|e| format!("unexpected reply from server: {}", e))));
+    });
+}
@@ -0,0 +1,28 @@
+#[macro_use]
+extern crate serde_derive;
+// only the browser build needs it, to ask javascript for the time
+#[cfg(all(target_arch = "wasm32", feature = "browser"))]
+#[macro_use]
+extern crate stdweb;
+extern crate serde;
+extern crate serde_json;
+extern crate brotli;
+extern crate bincode;
+extern crate chrono;
+extern crate time;
+
+// the game itself, along with its replays and scores, kept apart from
+// anything that draws to the page or needs a browser so the leaderboard
+// server can use it too
+pub mod snake;
+pub mod graphics_data;
+pub mod grid;
+pub mod js_utils;
+pub mod game_mode;
+pub mod rng;
+pub mod puzzle;
+pub mod portal;
+pub mod events;
+pub mod scores;
+pub mod replay;
+pub mod leaderboard;
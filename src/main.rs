@@ -1,63 +1,240 @@
+#![recursion_limit = "1024"]
+
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
 extern crate stdweb;
-extern crate brotli;
+extern crate serde;
+extern crate serde_json;
 extern crate bincode;
 extern crate chrono;
 extern crate time;
+extern crate web_snake;
 
 use stdweb::{initialize, event_loop};
 use stdweb::web::{self, document, IParentNode, IEventTarget, INode, Element, IElement, IHtmlElement};
-use stdweb::web::html_element::{CanvasElement, InputElement};
-use stdweb::web::event::{KeyDownEvent, ClickEvent, IMouseEvent, MouseDownEvent};
-use stdweb::traits::IKeyboardEvent;
+use stdweb::web::html_element::{CanvasElement, InputElement, SelectElement};
+use stdweb::web::event::{KeyDownEvent, KeyUpEvent, ClickEvent, IEvent, IMouseEvent, MouseDownEvent, ChangeEvent};
+use stdweb::web::event::{TouchStart, TouchMove, TouchEnd, TouchCancel};
+use stdweb::traits::{IKeyboardEvent, ITouchEvent};
 use stdweb::unstable::TryInto;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
-mod canvas;
-mod snake;
-mod graphics_data;
-mod js_utils;
+use web_snake::{snake, graphics_data, grid, js_utils, game_mode, rng, puzzle, portal, events, scores, replay, leaderboard};
+
+mod storage;
+mod saved_scores;
+mod solutions;
 mod triangle;
+mod input;
+mod canvas;
+mod daily;
+mod audio;
+mod achievements;
+mod share;
+mod leaderboard_client;
+mod keybindings;
+mod gamepad;
+mod touch;
+mod theme;
+mod accessibility;
+mod sprites;
+
+use game_mode::GameMode;
+use grid::Grid;
+use puzzle::PuzzlePack;
+use events::{EventBus, GameEvent};
+use achievements::Achievement;
+use keybindings::{Action, Input, KeyBindings};
+use scores::{ScoreView, SortBy};
+use touch::{SwipeTracker, TouchScheme};
+use theme::Theme;
+use accessibility::AccessibilitySettings;
+use sprites::{SnakeStyle, SpriteAtlas};
+use chrono::NaiveDate;
 
-use triangle::{Point, Triangle};
+// how many turns the rewind key goes back in practice games
+const REWIND_TURNS: usize = 5;
+const SAVED_GAME_KEY: &str = "saved-game";
 
 struct Cfg {
     canvas: CanvasElement,
-    width: u32,
-    height: u32,
-    game_frame_rate: u32,
     frame_rate: u32,
+    // what regular games are set up with, apart from the seed
+    settings: snake::GameSettings,
+    puzzles: Rc<PuzzlePack>,
+    // anything that wants to react to what happens in game subscribes here
+    events: Rc<EventBus>,
+    achievements: Rc<RefCell<achievements::Tracker>>,
+    scores: Rc<RefCell<ScoreView>>,
+    // the game the share button hands out a link to
+    last_result: Option<snake::GameResult>,
+    keys: Rc<RefCell<KeyBindings>>,
+    // the action waiting for a key press in the options panel
+    binding: Rc<Cell<Option<Action>>>,
+    touch: Rc<Cell<TouchScheme>>,
+    theme: Rc<RefCell<Theme>>,
+    accessibility: Rc<Cell<AccessibilitySettings>>,
+    snake_style: Rc<Cell<SnakeStyle>>,
+    atlas: Rc<SpriteAtlas>,
+}
+
+// what sort of game is being played, decides what happens to the result
+#[derive(Clone, Serialize, Deserialize)]
+enum GameKind {
+    Regular,
+    Daily(NaiveDate),
+    // the id of the puzzle being played
+    Puzzle(String),
+    // watching a recording, nothing to save or score
+    Replay,
+}
+
+// an unfinished game, saved when it gets paused or the page is closed
+#[derive(Serialize, Deserialize)]
+struct SavedSession {
+    kind: GameKind,
+    game: snake::SavedGame,
+}
+
+impl Cfg {
+    // settings for a regular game with a fresh random seed
+    fn game_settings(&self) -> snake::GameSettings {
+        snake::GameSettings {
+            seed: (random() * u32::max_value() as f64) as u64,
+            ..self.settings.clone()
+        }
+    }
 }
 
 // just ignore all of the Rc<RefCell>>... rust isn't aware that it's
 // impossible to be multi-threaded in this wacky javascript world
-fn run_snake_game<F>(cfg_cell: &Rc<RefCell<Cfg>>, res: F)
+fn run_snake_game<F>(cfg_cell: &Rc<RefCell<Cfg>>, game: snake::SnakeGameLogic, kind: GameKind, res: F)
     where F: FnOnce(Result<snake::GameResult, String>) + 'static {
     let cfg = cfg_cell.borrow_mut();
 
-    let snake_game = Rc::new(
-        RefCell::new(snake::SnakeGameLogic::new(cfg.width, cfg.height, cfg.game_frame_rate))
-    );
+    let snake_game = Rc::new(RefCell::new(game));
+    snake_game.borrow_mut().set_reduced_motion(cfg.accessibility.get().reduced_motion);
 
+    // hang on to the game if the page gets closed part way through
+    let save_game = {
+        let snake = snake_game.clone();
+        let kind = kind.clone();
+        move || {
+            save_session(&kind, &snake.borrow());
+        }
+    };
+    js! {
+        var save_game = @{save_game};
+        window.addEventListener("beforeunload", function() {
+            save_game();
+        });
+    }
+
+    // the keyboard and controllers both end up here
+    let perform: Rc<dyn Fn(Input)> = Rc::new({
+        let snake = snake_game.clone();
+        let tracker = cfg.achievements.clone();
+        let accessibility = cfg.accessibility.clone();
+        let kind = kind.clone();
+        let puzzles = cfg.puzzles.clone();
+        move |input: Input| {
+            let mut snake = snake.borrow_mut();
+            let action = match input {
+                Input::Pressed(action) => action,
+                Input::Released(Action::Boost) => {
+                    snake.set_boost(false);
+                    return;
+                },
+                Input::Released(_) => return,
+            };
+            match action {
+                Action::Rewind => {
+                    snake.rewind(REWIND_TURNS);
+                },
+                Action::Pause => {
+                    snake.toggle_pause();
+                    if snake.is_paused() {
+                        save_session(&kind, &snake);
+                    }
+                },
+                Action::Restart => {
+                    // the abandoned attempt isn't scored, it just goes away
+                    if snake.end().is_none() {
+                        // always from the very start, going back to where a saved game was
+                        // resumed would be a rewind
+                        let restarted = match kind {
+                            GameKind::Puzzle(ref id) => puzzles.puzzles.iter()
+                                .find(|p| p.id == *id)
+                                .map(snake::SnakeGameLogic::from_puzzle),
+                            _ => snake.restart(),
+                        };
+                        let restarted = match restarted {
+                            Some(game) => game,
+                            None => return,
+                        };
+                        *snake = restarted;
+                        snake.set_reduced_motion(accessibility.get().reduced_motion);
+                        storage::remove(SAVED_GAME_KEY);
+                        show_resume_button(false);
+                        tracker.borrow_mut().start_game(snake.is_practice() || snake.is_replay());
+                    }
+                },
+                Action::EndGame => snake.finish(),
+                Action::Boost => snake.set_boost(true),
+                action => {
+                    if let Some(direction) = action.direction() {
+                        snake.press_key(direction);
+                    } else if let Some(rotation) = action.rotation() {
+                        snake.turn(rotation);
+                    }
+                },
+            }
+        }
+    });
     web::window().add_event_listener({
+        let keys = cfg.keys.clone();
         let snake = snake_game.clone();
+        let perform = perform.clone();
         // only listen for key down events because the key press event has a 300ms delay
         move |event: KeyDownEvent| {
-            let mut snake = snake.borrow_mut();
-            match event.key().as_ref() {
-                "w" | "W" | "ArrowUp" => snake.press_key(snake::MoveDirection::Up),
-                "s" | "S" | "ArrowDown" => snake.press_key(snake::MoveDirection::Down),
-                "a" | "A" | "ArrowLeft" => snake.press_key(snake::MoveDirection::Left),
-                "d" | "D" | "ArrowRight" => snake.press_key(snake::MoveDirection::Right),
-                _ => {},
+            // the options panel already used this one to bind a key
+            if event.default_prevented() || typing(&event) {
+                return;
+            }
+            let action = keys.borrow().action_for(&event.code(), &event.key());
+            if let Some(action) = action {
+                // stop the arrows and space scrolling the page or pressing a focused button mid game
+                if snake.borrow().end().is_none() {
+                    event.prevent_default();
+                }
+                // a held down pause key would otherwise flicker between paused and not
+                if event.repeat() && action.toggles() {
+                    return;
+                }
+                perform(Input::Pressed(action));
+            }
+        }
+    });
+    // games from before this one are already over, so their listeners don't do anything
+    document().query_selector("#end-button").unwrap().unwrap().add_event_listener({
+        let perform = perform.clone();
+        move |_: ClickEvent| perform(Input::Pressed(Action::EndGame))
+    });
+    web::window().add_event_listener({
+        let keys = cfg.keys.clone();
+        let perform = perform.clone();
+        move |event: KeyUpEvent| {
+            let action = keys.borrow().action_for(&event.code(), &event.key());
+            if let Some(action) = action {
+                perform(Input::Released(action));
             }
         }
     });
 
-    let snake_canvas = match canvas::Canvas::new(cfg.canvas.clone(), cfg.frame_rate) {
+    let snake_canvas = match canvas::Canvas::new(cfg.canvas.clone(), cfg.frame_rate, cfg.theme.clone(),
+                                                cfg.snake_style.clone(), cfg.atlas.clone()) {
         Ok(c) => c,
         Err(e) => {
             res(Err(e));
@@ -65,51 +242,90 @@ fn run_snake_game<F>(cfg_cell: &Rc<RefCell<Cfg>>, res: F)
         }
     };
 
-    // disgusting mouse click calculations...
-    // there is definitely a better way to do this
+    // swiping takes over touches completely, so no pretend mouse clicks come after
+    // them, the zones below still work with a real mouse
+    let swipe = Rc::new(RefCell::new(SwipeTracker::new()));
+    cfg.canvas.clone().add_event_listener({
+        let swipe = swipe.clone();
+        let scheme = cfg.touch.clone();
+        move |event: TouchStart| {
+            if scheme.get() != TouchScheme::Swipe {
+                return;
+            }
+            event.prevent_default();
+            let time = event.time_stamp().unwrap_or(0.0);
+            for touch in event.changed_touches() {
+                swipe.borrow_mut().start(touch.identifier(), touch.client_x(), touch.client_y(), time);
+            }
+        }
+    });
+    cfg.canvas.clone().add_event_listener({
+        let swipe = swipe.clone();
+        let scheme = cfg.touch.clone();
+        let snake = snake_game.clone();
+        let perform = perform.clone();
+        move |event: TouchMove| {
+            if scheme.get() != TouchScheme::Swipe {
+                return;
+            }
+            event.prevent_default();
+            let time = event.time_stamp().unwrap_or(0.0);
+            let grid = snake.borrow().grid();
+            for touch in event.changed_touches() {
+                let swiped = swipe.borrow_mut().move_to(touch.identifier(), touch.client_x(), touch.client_y(), time, grid);
+                if let Some(direction) = swiped {
+                    perform(Input::Pressed(Action::steer(direction)));
+                }
+            }
+        }
+    });
+    let end_swipe = {
+        let swipe = swipe.clone();
+        move |touches: Vec<stdweb::web::Touch>| {
+            for touch in touches {
+                swipe.borrow_mut().end(touch.identifier());
+            }
+        }
+    };
+    cfg.canvas.clone().add_event_listener({
+        let end_swipe = end_swipe.clone();
+        move |event: TouchEnd| end_swipe(event.changed_touches())
+    });
+    cfg.canvas.clone().add_event_listener(move |event: TouchCancel| end_swipe(event.changed_touches()));
+
+    // taps that aren't swipes, and mouse clicks, see `input::Screen` for which way they go
     cfg.canvas.clone().add_event_listener({
         let snake = snake_game.clone();
         let canvas = cfg.canvas.clone();
+        let scheme = cfg.touch.clone();
+        let perform = perform.clone();
         move |event: MouseDownEvent| {
-            let mut snake = snake.borrow_mut();
             let bounding_rect = canvas.get_bounding_client_rect();
-            let click_point = Point::new(
-                event.client_x() - bounding_rect.get_left() as i32,
-                event.client_y() - bounding_rect.get_top() as i32
-            );
-
-            let width = canvas.width() as i32;
-            let height = canvas.height() as i32;
-
-            let up_triangle = Triangle::new(
-                Point::new(0, 0),
-                Point::new(width, 0),
-                Point::new(width/2, height/2),
-            );
-            let down_triangle = Triangle::new(
-                Point::new(0, height),
-                Point::new(width, height),
-                Point::new(width/2, height/2),
+            let click_point = (
+                event.client_x() as f64 - bounding_rect.get_left(),
+                event.client_y() as f64 - bounding_rect.get_top(),
             );
-            let left_triangle = Triangle::new(
-                Point::new(0, 0),
-                Point::new(0, height),
-                Point::new(width/2, height/2),
-            );
-            let right_triangle = Triangle::new(
-                Point::new(width, 0),
-                Point::new(width, height),
-                Point::new(width/2, height/2),
-            );
-
-            if up_triangle.contains(&click_point) {
-                snake.press_key(snake::MoveDirection::Up)
-            } else if down_triangle.contains(&click_point) {
-                snake.press_key(snake::MoveDirection::Down)
-            } else if left_triangle.contains(&click_point) {
-                snake.press_key(snake::MoveDirection::Left)
-            } else if right_triangle.contains(&click_point) {
-                snake.press_key(snake::MoveDirection::Right)
+            let action = {
+                let snake = snake.borrow();
+                let (columns, rows) = snake.size();
+                let screen = input::Screen {
+                    grid: snake.grid(),
+                    columns,
+                    rows,
+                    width: canvas.width() as f64,
+                    height: canvas.height() as f64,
+                };
+                match scheme.get() {
+                    TouchScheme::Head => screen.towards_head(snake.head(), click_point).map(Action::steer),
+                    TouchScheme::Turns => Some(match screen.turn_for(click_point) {
+                        snake::Rotation::Clockwise => Action::TurnRight,
+                        snake::Rotation::CounterClockwise => Action::TurnLeft,
+                    }),
+                    TouchScheme::Zones | TouchScheme::Swipe => screen.zone_direction(click_point).map(Action::steer),
+                }
+            };
+            if let Some(action) = action {
+                perform(Input::Pressed(action));
             }
         }
     });
@@ -118,12 +334,22 @@ fn run_snake_game<F>(cfg_cell: &Rc<RefCell<Cfg>>, res: F)
     // recursive main loop
     // no stack overflow because we are throwing the function calls in the event 
     // loop & deconstructing the stack with set_timeout every iteration
-    fn main_loop<F>(c: Rc<RefCell<canvas::Canvas>>, s: Rc<RefCell<snake::SnakeGameLogic>>, res: F)
+    fn main_loop<F>(c: Rc<RefCell<canvas::Canvas>>, s: Rc<RefCell<snake::SnakeGameLogic>>, bus: Rc<EventBus>,
+                    pad: Rc<RefCell<gamepad::Gamepad>>, keys: Rc<RefCell<KeyBindings>>, perform: Rc<dyn Fn(Input)>, res: F)
         where F: FnOnce(Result<snake::GameResult, String>) + 'static {
+        // controllers don't send events for buttons, they have to be checked on
+        let grid = s.borrow().grid();
+        let inputs = pad.borrow_mut().poll(grid);
+        for input in inputs {
+            perform(input);
+        }
         let snake_ref = s.clone();
-        let wait_time = {
+        let (wait_time, events) = {
             let mut snake_game = snake_ref.borrow_mut();
-            match snake_game.advance() {
+            let result = snake_game.advance();
+            show_status(&snake_game, &keys.borrow());
+            let events = snake_game.drain_events();
+            let wait_time = match result {
                 Ok(d) => {
                     let canvas_ref = c.clone();
                     let mut canvas = canvas_ref.borrow_mut();
@@ -135,11 +361,14 @@ fn run_snake_game<F>(cfg_cell: &Rc<RefCell<Cfg>>, res: F)
                     canvas.render(&d);
                     None
                 }
-            }
+            };
+            (wait_time, events)
         };
+        // the game isn't borrowed anymore, so subscribers are free to poke at it
+        bus.publish(&events);
         if let Some(t) = wait_time {
             web::set_timeout(move || {
-                main_loop(c.clone(), s.clone(), res);
+                main_loop(c.clone(), s.clone(), bus, pad, keys, perform, res);
             }, t.abs() as u32);
         } else {
             let mut snake_game = snake_ref.borrow_mut();
@@ -147,7 +376,58 @@ fn run_snake_game<F>(cfg_cell: &Rc<RefCell<Cfg>>, res: F)
         }
     };
 
-    main_loop(snake_canvas, snake_game, res);
+    let pad = Rc::new(RefCell::new(gamepad::Gamepad::new()));
+    main_loop(snake_canvas, snake_game, cfg.events.clone(), pad, cfg.keys.clone(), perform, res);
+}
+
+// let the player know how much time or how many moves they have left, if the game mode has a limit
+fn show_status(snake_game: &snake::SnakeGameLogic, keys: &KeyBindings) {
+    let status = document().query_selector("#status").unwrap().unwrap();
+    let mut text = String::new();
+    if let Some(t) = snake_game.time_remaining() {
+        text.push_str(format!("time left: {}s ", t.as_secs()).as_ref());
+    }
+    if let Some(m) = snake_game.moves_remaining() {
+        text.push_str(format!("moves left: {} ", m).as_ref());
+    }
+    if let Some(lead) = snake_game.ghost_lead() {
+        let ghost = match lead {
+            0 => String::from("level with the ghost "),
+            lead if lead > 0 => format!("{} apples ahead of the ghost ", lead),
+            lead => format!("{} apples behind the ghost ", -lead),
+        };
+        text.push_str(ghost.as_ref());
+    }
+    if snake_game.is_paused() {
+        text.push_str(format!("paused (press {} to continue)", keys.describe(Action::Pause)).as_ref());
+    }
+    status.set_text_content(text.as_ref());
+}
+
+fn save_session(kind: &GameKind, snake_game: &snake::SnakeGameLogic) {
+    // finished games have nothing to come back to
+    if snake_game.end().is_some() || snake_game.is_replay() {
+        return;
+    }
+    let session = SavedSession {
+        kind: kind.clone(),
+        game: snake_game.save(),
+    };
+    if let Err(e) = storage::save(SAVED_GAME_KEY, &session) {
+        console!(error, e);
+    }
+}
+
+fn show_resume_button(visible: bool) {
+    let button = document().query_selector("#resume-button").unwrap().unwrap();
+    let style = if visible { "display: inline;" } else { "display: none;" };
+    button.set_attribute("style", style).expect("failed to set css attribute");
+}
+
+fn show_end_button(visible: bool) {
+    let button = document().query_selector("#end-button").unwrap().unwrap();
+    let style = if visible { "display: inline;" } else { "display: none;" };
+    button.set_attribute("style", style).expect("failed to set css attribute");
 }
 
 fn toggle_display(n: &Element) {
@@ -167,10 +447,560 @@ fn set_game_in_progress(game_playing: Rc<RefCell<bool>>, val: bool) {
     *playing = val;
 }
 
+fn random() -> f64 {
+    (js! {return Math.random()}).try_into().unwrap()
+}
+
 fn get_value(n: &InputElement) -> u32 {
     n.raw_value().parse().unwrap()
 }
 
+// kicks off a game unless one is already being played
+//
+// there is only one save slot, so starting a game throws away any saved one
+fn start_game(cfg: &Rc<RefCell<Cfg>>, game_playing: &Rc<RefCell<bool>>, game: snake::SnakeGameLogic, kind: GameKind) {
+    if game_in_progress(game_playing.clone()) {
+        return;
+    }
+    set_game_in_progress(game_playing.clone(), true);
+    storage::remove(SAVED_GAME_KEY);
+    show_resume_button(false);
+    cfg.borrow().achievements.borrow_mut().start_game(game.is_practice() || game.is_replay());
+    show_end_button(true);
+    let cfg_ref = cfg.clone();
+    let game_playing = game_playing.clone();
+    run_snake_game(cfg, game, kind.clone(), move |res| {
+        set_game_in_progress(game_playing.clone(), false);
+        show_end_button(false);
+        match res {
+            Err(e) => {
+                web::window().alert(e.as_ref());
+            }
+            Ok(r) => game_over(&cfg_ref, &game_playing, &kind, r),
+        }
+    });
+}
+
+fn game_over(cfg: &Rc<RefCell<Cfg>>, game_playing: &Rc<RefCell<bool>>, kind: &GameKind, r: snake::GameResult) {
+    // anything saved on the way here is from before the game ended, resuming
+    // it would be a free retry
+    storage::remove(SAVED_GAME_KEY);
+    show_resume_button(false);
+    if let GameKind::Replay = *kind {
+        // watching a game back doesn't earn anything
+    } else {
+        award(&achievements::earned_by(&r));
+    }
+    if let GameKind::Puzzle(_) = *kind {
+        // puzzles start from their own layout, so a link couldn't rebuild them
+    } else {
+        cfg.borrow_mut().last_result = Some(r.clone());
+        show_share_button();
+    }
+    match *kind {
+        GameKind::Regular => add_score(cfg, game_playing, &r),
+        GameKind::Daily(date) => {
+            add_score(cfg, game_playing, &r);
+            if let Err(e) = daily::record_result(date, r) {
+                web::window().alert(e.as_ref());
+            }
+            show_daily_history();
+        }
+        GameKind::Puzzle(ref id) => {
+            let pack = cfg.borrow().puzzles.clone();
+            if let Some(puzzle) = pack.puzzles.iter().find(|p| p.id == *id) {
+                if r.end == Some(snake::GameEnd::Cleared) {
+                    let saved = r.moves().and_then(|moves| solutions::save_solution(puzzle, moves));
+                    if let Err(e) = saved {
+                        web::window().alert(format!("failed to save puzzle solution: {}", e).as_ref());
+                    }
+                }
+            }
+            show_puzzle_list(cfg, game_playing);
+        }
+        GameKind::Replay => {}
+    }
+}
+
+// one row per puzzle in the pack, click on one to play it
+fn show_puzzle_list(cfg: &Rc<RefCell<Cfg>>, game_playing: &Rc<RefCell<bool>>) {
+    let pack = cfg.borrow().puzzles.clone();
+    let list = document().query_selector("#puzzle-list").unwrap().unwrap();
+    while let Some(child) = list.first_child() {
+        list.remove_child(&child).unwrap();
+    }
+
+    for puzzle in pack.puzzles.iter() {
+        let state = if solutions::is_solved(puzzle) { "solved" } else { "unsolved" };
+        let button = document().create_element("button").unwrap();
+        button.set_attribute("class", "inline-button").expect("failed to set css class");
+        button.set_text_content(format!("{} ({})", puzzle.name, state).as_ref());
+        button.add_event_listener({
+            let cfg = cfg.clone();
+            let game_playing = game_playing.clone();
+            let puzzle = puzzle.clone();
+            move |_: ClickEvent| {
+                let game = snake::SnakeGameLogic::from_puzzle(&puzzle);
+                start_game(&cfg, &game_playing, game, GameKind::Puzzle(puzzle.id.clone()));
+            }
+        });
+        let row = document().create_element("div").unwrap();
+        row.append_child(&button);
+        list.append_child(&row);
+    }
+}
+
+fn add_score(cfg: &Rc<RefCell<Cfg>>, game_playing: &Rc<RefCell<bool>>, r: &snake::GameResult) {
+    // rewound games stay off the scoreboard
+    if r.practice {
+        return;
+    }
+    if let Err(e) = saved_scores::record(r.clone()) {
+        web::window().alert(format!("failed to save score: {}", e).as_ref());
+    }
+    show_scores(cfg, game_playing);
+    submit_to_leaderboard(r);
+}
+
+// the server plays the game back before it believes the score, so this can take a moment
+fn submit_to_leaderboard(r: &snake::GameResult) {
+    let settings = leaderboard_client::load_settings();
+    if settings.url.is_empty() || r.settings.practice {
+        return;
+    }
+    leaderboard_client::submit(&settings, r, |res| {
+        match res {
+            Ok(accepted) => show_toast(format!("#{} on the {} leaderboard", accepted.rank, accepted.configuration).as_ref()),
+            Err(e) => show_toast(format!("leaderboard didn't take the score: {}", e).as_ref()),
+        }
+    });
+}
+
+fn show_leaderboards() {
+    let list = document().query_selector("#leaderboard-list").unwrap().unwrap();
+    let settings = leaderboard_client::load_settings();
+    if settings.url.is_empty() {
+        list.set_text_content("set a leaderboard server in the options to see it here");
+        return;
+    }
+    list.set_text_content("loading...");
+    leaderboard_client::fetch_leaderboards(&settings, move |res| {
+        let leaderboards = match res {
+            Ok(l) => l,
+            Err(e) => {
+                list.set_text_content(e.as_ref());
+                return;
+            }
+        };
+        clear_children(&list);
+        if leaderboards.is_empty() {
+            list.set_text_content("nobody has submitted a score yet");
+        }
+        for leaderboard in leaderboards.iter() {
+            let h = document().create_element("h4").unwrap();
+            h.set_text_content(leaderboard.configuration.as_ref());
+            list.append_child(&h);
+            for (i, score) in leaderboard.scores.iter().enumerate() {
+                let p = document().create_element("p").unwrap();
+                p.set_text_content(format!("{}. {} - {} ({} apples, {} turns) on {}",
+                                           i + 1, score.name, score.score, score.apples_eaten, score.turns_passed, score.date.date()).as_ref());
+                list.append_child(&p);
+            }
+        }
+    });
+}
+
+fn clear_children<N: INode>(element: &N) {
+    while let Some(child) = element.first_child() {
+        element.remove_child(&child).unwrap();
+    }
+}
+
+fn format_duration(duration: std::time::Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// redraws the saved scores the way the view says to, along with the best of each configuration
+fn show_scores(cfg: &Rc<RefCell<Cfg>>, game_playing: &Rc<RefCell<bool>>) {
+    let view = cfg.borrow().scores.clone();
+    let table = saved_scores::load();
+
+    let bests = document().query_selector("#score-bests").unwrap().unwrap();
+    clear_children(&bests);
+    for (configuration, entry) in table.bests() {
+        let p = document().create_element("p").unwrap();
+        p.set_text_content(format!("{}: best {} on {}", configuration, entry.result.score, entry.date.date()).as_ref());
+        bests.append_child(&p);
+    }
+
+    // rebuilt every time since new configurations show up as games get played
+    let filter: SelectElement = document().query_selector("#score-filter").unwrap().unwrap().try_into().unwrap();
+    clear_children(&filter);
+    let all = document().create_element("option").unwrap();
+    all.set_attribute("value", "").expect("failed to set option value");
+    all.set_text_content("All configurations");
+    filter.append_child(&all);
+    for configuration in table.configurations() {
+        let option = document().create_element("option").unwrap();
+        option.set_attribute("value", configuration.as_ref()).expect("failed to set option value");
+        option.set_text_content(configuration.as_ref());
+        filter.append_child(&option);
+    }
+    let selected = view.borrow().filter.clone().unwrap_or_default();
+    js! { @{&filter}.value = @{selected}; }
+
+    let score_table = document().query_selector("#score-table").unwrap().unwrap();
+    clear_children(&score_table);
+    let header = document().create_element("tr").unwrap();
+    let columns = [
+        ("Date", Some(SortBy::Date)),
+        ("Score", Some(SortBy::Score)),
+        ("Mode", None),
+        ("Size", Some(SortBy::Size)),
+        ("Speed", Some(SortBy::Speed)),
+        ("Apples", Some(SortBy::Apples)),
+        ("Turns", Some(SortBy::Turns)),
+        ("Duration", Some(SortBy::Duration)),
+        ("", None),
+    ];
+    for &(name, sort) in columns.iter() {
+        let th = document().create_element("th").unwrap();
+        let arrow = match sort {
+            Some(sort) if sort == view.borrow().sort => if view.borrow().descending { " v" } else { " ^" },
+            _ => "",
+        };
+        th.set_text_content(format!("{}{}", name, arrow).as_ref());
+        if let Some(sort) = sort {
+            // sortable headers can be reached with tab and pressed with enter or space
+            th.set_attribute("tabindex", "0").expect("failed to set tabindex");
+            th.set_attribute("role", "button").expect("failed to set role");
+            th.add_event_listener({
+                let cfg = cfg.clone();
+                let game_playing = game_playing.clone();
+                move |_: ClickEvent| {
+                    cfg.borrow().scores.borrow_mut().sort_by(sort);
+                    show_scores(&cfg, &game_playing);
+                }
+            });
+            th.add_event_listener({
+                let cfg = cfg.clone();
+                let game_playing = game_playing.clone();
+                move |event: KeyDownEvent| {
+                    if event.key() == "Enter" || event.key() == " " {
+                        event.prevent_default();
+                        cfg.borrow().scores.borrow_mut().sort_by(sort);
+                        show_scores(&cfg, &game_playing);
+                    }
+                }
+            });
+        }
+        header.append_child(&th);
+    }
+    score_table.append_child(&header);
+
+    for entry in view.borrow().apply(&table) {
+        let r = &entry.result;
+        let row = document().create_element("tr").unwrap();
+        let cells = [
+            entry.date.format("%Y-%m-%d %H:%M").to_string(),
+            r.score.to_string(),
+            r.settings.mode.name().to_string(),
+            format!("{}x{}", r.width, r.height),
+            format!("{} fps", r.settings.frame_rate),
+            r.apples_eaten.to_string(),
+            r.turns_passed.to_string(),
+            format_duration(r.time_played),
+        ];
+        for cell in cells.iter() {
+            let td = document().create_element("td").unwrap();
+            td.set_text_content(cell.as_ref());
+            row.append_child(&td);
+        }
+        let watch = document().create_element("button").unwrap();
+        watch.set_text_content("Watch");
+        watch.add_event_listener({
+            let cfg = cfg.clone();
+            let game_playing = game_playing.clone();
+            let result = r.clone();
+            move |_: ClickEvent| {
+                watch_replay(&cfg, &game_playing, &result);
+            }
+        });
+        let race = document().create_element("button").unwrap();
+        race.set_text_content("Race");
+        race.add_event_listener({
+            let cfg = cfg.clone();
+            let game_playing = game_playing.clone();
+            let result = r.clone();
+            move |_: ClickEvent| {
+                race_ghost(&cfg, &game_playing, &result);
+            }
+        });
+        let download = document().create_element("button").unwrap();
+        download.set_text_content("Download");
+        download.add_event_listener({
+            let result = r.clone();
+            let name = format!("snake-{}.json", entry.date.format("%Y-%m-%d-%H%M%S"));
+            move |_: ClickEvent| {
+                download_file(&name, &replay::export_game(&result));
+            }
+        });
+        let delete = document().create_element("button").unwrap();
+        delete.set_text_content("Delete");
+        delete.add_event_listener({
+            let cfg = cfg.clone();
+            let game_playing = game_playing.clone();
+            let id = entry.id;
+            move |_: ClickEvent| {
+                if let Err(e) = saved_scores::delete(id) {
+                    web::window().alert(format!("failed to delete score: {}", e).as_ref());
+                }
+                show_scores(&cfg, &game_playing);
+            }
+        });
+        let td = document().create_element("td").unwrap();
+        td.append_child(&watch);
+        td.append_child(&race);
+        td.append_child(&download);
+        td.append_child(&delete);
+        row.append_child(&td);
+        score_table.append_child(&row);
+    }
+}
+
+fn watch_replay(cfg: &Rc<RefCell<Cfg>>, game_playing: &Rc<RefCell<bool>>, result: &snake::GameResult) {
+    match snake::SnakeGameLogic::replay(result) {
+        Ok(game) => start_game(cfg, game_playing, game, GameKind::Replay),
+        Err(e) => web::window().alert(format!("can't watch this replay: {}", e).as_ref()),
+    }
+}
+
+fn show_share_button() {
+    let button = document().query_selector("#share-button").unwrap().unwrap();
+    button.set_attribute("style", "display: inline;").expect("failed to set css attribute");
+    let link = document().query_selector("#share-link").unwrap().unwrap();
+    link.set_attribute("style", "display: none;").expect("failed to set css attribute");
+}
+
+fn race_ghost(cfg: &Rc<RefCell<Cfg>>, game_playing: &Rc<RefCell<bool>>, ghost: &snake::GameResult) {
+    match snake::SnakeGameLogic::race(ghost) {
+        Ok(game) => start_game(cfg, game_playing, game, GameKind::Regular),
+        Err(e) => web::window().alert(format!("can't race this replay: {}", e).as_ref()),
+    }
+}
+
+// hands the text to the browser as a file to save
+fn download_file(name: &str, contents: &str) {
+    js! {
+        var blob = new Blob([@{contents}], { type: "application/json" });
+        var link = document.createElement("a");
+        link.href = URL.createObjectURL(blob);
+        link.download = @{name};
+        document.body.appendChild(link);
+        link.click();
+        document.body.removeChild(link);
+        URL.revokeObjectURL(link.href);
+    }
+}
+
+// a single game gets played straight away, a score history gets added to ours
+fn import_replay_file(cfg: &Rc<RefCell<Cfg>>, game_playing: &Rc<RefCell<bool>>, text: &str) {
+    match replay::parse(text) {
+        Ok(replay::ReplayContents::Game(result)) => {
+            let race: bool = js! { return confirm("Race against this replay? Cancel to just watch it."); }.try_into().unwrap();
+            if race {
+                race_ghost(cfg, game_playing, &result);
+            } else {
+                watch_replay(cfg, game_playing, &result);
+            }
+        }
+        Ok(replay::ReplayContents::History(entries)) => {
+            match saved_scores::import(entries) {
+                Ok(n) => web::window().alert(format!("imported {} new scores", n).as_ref()),
+                Err(e) => web::window().alert(format!("failed to save imported scores: {}", e).as_ref()),
+            }
+            show_scores(cfg, game_playing);
+        }
+        Err(e) => web::window().alert(format!("couldn't load replay file: {}", e).as_ref()),
+    }
+}
+
+// list every day the daily challenge was played, along with the best one ever
+fn show_daily_history() {
+    let history = document().query_selector("#daily-history").unwrap().unwrap();
+    while let Some(child) = history.first_child() {
+        history.remove_child(&child).unwrap();
+    }
+
+    let results = daily::load_results();
+    let best = results.iter().max_by_key(|d| d.result.score);
+    let best_text = match best {
+        Some(d) => format!("best ever daily: {} on {}", d.result.score, d.date),
+        None => String::from("no daily challenges played yet"),
+    };
+    let best_p = document().create_element("p").unwrap();
+    best_p.set_text_content(best_text.as_ref());
+    history.append_child(&best_p);
+
+    let mut dates: Vec<_> = results.iter().map(|d| d.date).collect();
+    dates.sort();
+    dates.dedup();
+    for date in dates.iter().rev() {
+        let attempts: Vec<_> = results.iter().filter(|d| d.date == *date).collect();
+        let day_best = attempts.iter().map(|d| d.result.score).max().unwrap_or(0);
+        let day_p = document().create_element("p").unwrap();
+        day_p.set_text_content(format!("{}: best {} ({} attempts)", date, day_best, attempts.len()).as_ref());
+        history.append_child(&day_p);
+    }
+}
+
+// unlocks achievements, popping up a toast for each one that's new
+fn award(earned: &[Achievement]) {
+    match achievements::unlock(earned) {
+        Ok(new) => {
+            for achievement in new.iter() {
+                show_toast(format!("Achievement unlocked: {}!", achievement.name()).as_ref());
+            }
+            if !new.is_empty() {
+                show_achievements();
+            }
+        }
+        Err(e) => web::window().alert(format!("failed to save achievements: {}", e).as_ref()),
+    }
+}
+
+// a little message over the game that goes away by itself
+fn show_toast(text: &str) {
+    let toasts = document().query_selector("#toasts").unwrap().unwrap();
+    let toast = document().create_element("div").unwrap();
+    toast.set_attribute("class", "toast").expect("failed to set css class");
+    toast.set_text_content(text);
+    toasts.append_child(&toast);
+    js! {
+        var toast = @{toast};
+        setTimeout(function() { toast.remove(); }, 3000);
+    }
+}
+
+// the theme picker, plus the current theme as json to start a custom one from
+fn show_themes(cfg: &Rc<RefCell<Cfg>>) {
+    let select: SelectElement = document().query_selector("#theme").unwrap().unwrap().try_into().unwrap();
+    clear_children(&select);
+    let current = cfg.borrow().theme.borrow().clone();
+    for theme in theme::all() {
+        let option = document().create_element("option").unwrap();
+        option.set_attribute("value", &theme.name).expect("failed to set option value");
+        option.set_text_content(&theme.name);
+        select.append_child(&option);
+    }
+    select.set_raw_value(&current.name);
+    let json = serde_json::to_string_pretty(&current).expect("failed to serialize theme");
+    js! { @(no_return) document.getElementById("custom-theme").value = @{json}; }
+}
+
+fn use_theme(cfg: &Rc<RefCell<Cfg>>, theme: Theme) {
+    if let Err(e) = theme::select(&theme) {
+        web::window().alert(format!("failed to save theme: {}", e).as_ref());
+    }
+    apply_theme(&cfg.borrow().canvas, &theme);
+    *cfg.borrow().theme.borrow_mut() = theme;
+}
+
+// the bits of the page around the board that follow the theme
+fn apply_theme(canvas: &CanvasElement, theme: &Theme) {
+    js! { @(no_return)
+        var canvas = @{canvas};
+        canvas.style.outlineColor = @{&theme.ui};
+        canvas.style.backgroundColor = @{&theme.background};
+    }
+}
+
+// one row per action, with its keys and a button to add another
+// keys pressed while filling in the options are for the options, not the snake
+fn typing(event: &KeyDownEvent) -> bool {
+    match event.target() {
+        Some(target) => js! {
+            var tag = @{target}.tagName;
+            return tag === "INPUT" || tag === "TEXTAREA" || tag === "SELECT";
+        }.try_into().unwrap_or(false),
+        None => false,
+    }
+}
+
+fn show_key_bindings(cfg: &Rc<RefCell<Cfg>>) {
+    let table = document().query_selector("#key-bindings").unwrap().unwrap();
+    clear_children(&table);
+    let (keys, binding) = {
+        let cfg = cfg.borrow();
+        (cfg.keys.clone(), cfg.binding.clone())
+    };
+    let help = document().query_selector("#controls-help").unwrap().unwrap();
+    help.set_text_content(keys.borrow().help().as_ref());
+
+    for &action in keybindings::ALL_ACTIONS.iter() {
+        let row = document().create_element("tr").unwrap();
+        let name = document().create_element("td").unwrap();
+        name.set_text_content(action.name());
+        row.append_child(&name);
+
+        let cell = document().create_element("td").unwrap();
+        for key in keys.borrow().keys(action).iter() {
+            let button = document().create_element("button").unwrap();
+            button.set_attribute("class", "inline-button").expect("failed to set css class");
+            button.set_attribute("title", "click to remove").expect("failed to set title");
+            button.set_text_content(format!("{} \u{2715}", keybindings::label(key)).as_ref());
+            button.add_event_listener({
+                let cfg = cfg.clone();
+                let keys = keys.clone();
+                let key = key.clone();
+                move |_: ClickEvent| {
+                    keys.borrow_mut().remove(action, &key);
+                    if let Err(e) = keys.borrow().save() {
+                        web::window().alert(format!("failed to save key bindings: {}", e).as_ref());
+                    }
+                    show_key_bindings(&cfg);
+                }
+            });
+            cell.append_child(&button);
+        }
+        let add = document().create_element("button").unwrap();
+        add.set_attribute("class", "inline-button").expect("failed to set css class");
+        let waiting = binding.get() == Some(action);
+        add.set_text_content(if waiting { "Press a key..." } else { "Add Key" });
+        add.add_event_listener({
+            let cfg = cfg.clone();
+            let binding = binding.clone();
+            move |_: ClickEvent| {
+                // clicking again gives up on it
+                binding.set(if binding.get() == Some(action) { None } else { Some(action) });
+                show_key_bindings(&cfg);
+            }
+        });
+        cell.append_child(&add);
+        row.append_child(&cell);
+        table.append_child(&row);
+    }
+}
+
+fn show_achievements() {
+    let list = document().query_selector("#achievement-list").unwrap().unwrap();
+    while let Some(child) = list.first_child() {
+        list.remove_child(&child).unwrap();
+    }
+
+    let unlocked = achievements::load_unlocked();
+    for achievement in achievements::ALL_ACHIEVEMENTS.iter() {
+        let state = match unlocked.get(achievement) {
+            Some(date) => format!("unlocked {}", date),
+            None => String::from("locked"),
+        };
+        let p = document().create_element("p").unwrap();
+        p.set_text_content(format!("{}: {} ({})", achievement.name(), achievement.description(), state).as_ref());
+        list.append_child(&p);
+    }
+}
+
 fn main() {
     initialize();
 
@@ -183,37 +1013,305 @@ fn main() {
         .try_into()
         .unwrap();
     let cfg = Rc::new(RefCell::new(Cfg {
-        width: 8,
-        height: 6,
-        game_frame_rate: 4,
         frame_rate: 60,
+        settings: snake::GameSettings::default(),
+        puzzles: Rc::new(PuzzlePack::builtin()),
+        events: Rc::new(EventBus::new()),
+        achievements: Rc::new(RefCell::new(achievements::Tracker::new())),
+        scores: Rc::new(RefCell::new(ScoreView::new())),
+        last_result: None,
+        keys: Rc::new(RefCell::new(KeyBindings::load())),
+        binding: Rc::new(Cell::new(None)),
+        touch: Rc::new(Cell::new(touch::load_scheme())),
+        theme: Rc::new(RefCell::new(theme::load_selected())),
+        accessibility: Rc::new(Cell::new(accessibility::load_settings())),
+        snake_style: Rc::new(Cell::new(sprites::load_style())),
+        atlas: Rc::new(SpriteAtlas::load()),
         canvas: canvas.clone(),
     }));
 
+    // give phones a little buzz when something happens
+    cfg.borrow().events.subscribe(|event| {
+        let pattern = match *event {
+            GameEvent::AppleEaten { .. } => 30,
+            GameEvent::Died(_) => 300,
+            _ => return,
+        };
+        js! {
+            if (navigator.vibrate) {
+                navigator.vibrate(@{pattern});
+            }
+        }
+    });
+
+    // read out what's going on for anyone who can't see the board
+    cfg.borrow().events.subscribe({
+        let settings = cfg.borrow().accessibility.clone();
+        move |event| {
+            if !settings.get().announce {
+                return;
+            }
+            if let Some(text) = accessibility::announcement(event) {
+                accessibility::announce(&text);
+            }
+        }
+    });
+
+    let audio = Rc::new(audio::Audio::new());
+    cfg.borrow().events.subscribe({
+        let audio = audio.clone();
+        move |event| {
+            if let Some(tones) = audio::sound_for(event) {
+                audio.play(&tones);
+            }
+        }
+    });
+
+    // sound options take effect straight away rather than waiting for submit
+    let option_volume: InputElement = document().query_selector("#volume").unwrap().unwrap().try_into().unwrap();
+    let option_mute: InputElement = document().query_selector("#mute").unwrap().unwrap().try_into().unwrap();
+    option_volume.set_raw_value(&((audio.settings().volume * 100.0).round() as u32).to_string());
+    js! { @{&option_mute}.checked = @{audio.settings().muted}; }
+    let update_audio = {
+        let audio = audio.clone();
+        let option_volume = option_volume.clone();
+        let option_mute = option_mute.clone();
+        move |_: ChangeEvent| {
+            let volume: u32 = get_value(&option_volume);
+            let settings = audio::AudioSettings {
+                volume: f64::from(volume.min(100)) / 100.0,
+                muted: js! { return @{&option_mute}.checked; }.try_into().unwrap(),
+            };
+            if let Err(e) = audio.set_settings(settings) {
+                web::window().alert(format!("failed to save sound options: {}", e).as_ref());
+            }
+        }
+    };
+    option_volume.add_event_listener(update_audio.clone());
+    option_mute.add_event_listener(update_audio);
+
+    // leaderboard options are saved as soon as they change, like the sound ones
+    let option_leaderboard_url: InputElement = document().query_selector("#leaderboard-url").unwrap().unwrap().try_into().unwrap();
+    let option_player_name: InputElement = document().query_selector("#player-name").unwrap().unwrap().try_into().unwrap();
+    let leaderboard_settings = leaderboard_client::load_settings();
+    option_leaderboard_url.set_raw_value(&leaderboard_settings.url);
+    option_player_name.set_raw_value(&leaderboard_settings.name);
+    let update_leaderboard = {
+        let option_leaderboard_url = option_leaderboard_url.clone();
+        let option_player_name = option_player_name.clone();
+        move |_: ChangeEvent| {
+            let settings = leaderboard_client::LeaderboardSettings {
+                url: option_leaderboard_url.raw_value().trim().to_string(),
+                name: option_player_name.raw_value().trim().to_string(),
+            };
+            if let Err(e) = leaderboard_client::save_settings(&settings) {
+                web::window().alert(format!("failed to save leaderboard options: {}", e).as_ref());
+            }
+        }
+    };
+    option_leaderboard_url.add_event_listener(update_leaderboard.clone());
+    option_player_name.add_event_listener(update_leaderboard);
+
+    // let the player know their controller was noticed, it only shows up once a button is pressed
+    let gamepad_changed = |connected: bool, name: String| {
+        let status = document().query_selector("#gamepad-status").unwrap().unwrap();
+        if connected {
+            show_toast(format!("controller connected: {}", name).as_ref());
+            status.set_text_content("\u{1F3AE}");
+            status.set_attribute("title", name.as_ref()).expect("failed to set title");
+        } else {
+            show_toast(format!("controller disconnected: {}", name).as_ref());
+            status.set_text_content("");
+        }
+    };
+    js! {
+        var changed = @{gamepad_changed};
+        window.addEventListener("gamepadconnected", function(e) {
+            changed(true, e.gamepad.id);
+        });
+        window.addEventListener("gamepaddisconnected", function(e) {
+            changed(false, e.gamepad.id);
+        });
+    }
+
+    // themes apply straight away as well, the canvas reads the same one every frame
+    apply_theme(&cfg.borrow().canvas, &cfg.borrow().theme.borrow());
+    show_themes(&cfg);
+    let option_theme: SelectElement = document().query_selector("#theme").unwrap().unwrap().try_into().unwrap();
+    option_theme.add_event_listener({
+        let cfg = cfg.clone();
+        let option_theme = option_theme.clone();
+        move |_: ChangeEvent| {
+            let name = option_theme.value().unwrap_or_default();
+            if let Some(theme) = theme::all().into_iter().find(|t| t.name == name) {
+                use_theme(&cfg, theme);
+            }
+        }
+    });
+    let button = document().query_selector("#add-theme").unwrap().unwrap();
+    button.add_event_listener({
+        let cfg = cfg.clone();
+        move |_: ClickEvent| {
+            let json: String = js! { return document.getElementById("custom-theme").value; }.try_into().unwrap();
+            match theme::add_custom(&json) {
+                Ok(theme) => {
+                    use_theme(&cfg, theme);
+                    show_themes(&cfg);
+                }
+                Err(e) => web::window().alert(e.as_ref()),
+            }
+        }
+    });
+
+    let option_snake_style: SelectElement = document().query_selector("#snake-style").unwrap().unwrap().try_into().unwrap();
+    option_snake_style.set_raw_value(cfg.borrow().snake_style.get().name());
+    option_snake_style.add_event_listener({
+        let cfg = cfg.clone();
+        let option_snake_style = option_snake_style.clone();
+        move |_: ChangeEvent| {
+            let style = option_snake_style.value()
+                .and_then(|s| SnakeStyle::from_name(s.as_ref()))
+                .unwrap_or_default();
+            cfg.borrow().snake_style.set(style);
+            if let Err(e) = sprites::save_style(style) {
+                web::window().alert(format!("failed to save snake style: {}", e).as_ref());
+            }
+        }
+    });
+
+    // accessibility options are saved straight away, reduced motion kicks in from the next game
+    let option_reduced_motion: InputElement = document().query_selector("#reduced-motion").unwrap().unwrap().try_into().unwrap();
+    let option_announce: InputElement = document().query_selector("#announce").unwrap().unwrap().try_into().unwrap();
+    js! { @{&option_reduced_motion}.checked = @{cfg.borrow().accessibility.get().reduced_motion}; }
+    js! { @{&option_announce}.checked = @{cfg.borrow().accessibility.get().announce}; }
+    let update_accessibility = {
+        let cfg = cfg.clone();
+        let option_reduced_motion = option_reduced_motion.clone();
+        let option_announce = option_announce.clone();
+        move |_: ChangeEvent| {
+            let settings = AccessibilitySettings {
+                reduced_motion: js! { return @{&option_reduced_motion}.checked; }.try_into().unwrap(),
+                announce: js! { return @{&option_announce}.checked; }.try_into().unwrap(),
+            };
+            cfg.borrow().accessibility.set(settings);
+            if let Err(e) = accessibility::save_settings(settings) {
+                web::window().alert(format!("failed to save accessibility options: {}", e).as_ref());
+            }
+        }
+    };
+    option_reduced_motion.add_event_listener(update_accessibility.clone());
+    option_announce.add_event_listener(update_accessibility);
+
+    // touch controls change straight away too, even part way through a game
+    let option_touch: SelectElement = document().query_selector("#touch-scheme").unwrap().unwrap().try_into().unwrap();
+    option_touch.set_raw_value(cfg.borrow().touch.get().name());
+    option_touch.add_event_listener({
+        let cfg = cfg.clone();
+        let option_touch = option_touch.clone();
+        move |_: ChangeEvent| {
+            let scheme = option_touch.value()
+                .and_then(|s| TouchScheme::from_name(s.as_ref()))
+                .unwrap_or_default();
+            cfg.borrow().touch.set(scheme);
+            if let Err(e) = touch::save_scheme(scheme) {
+                web::window().alert(format!("failed to save touch controls: {}", e).as_ref());
+            }
+        }
+    });
+
+    // whichever action is waiting in the options panel gets the next key pressed,
+    // otherwise the restart key starts a game when there isn't one going
+    show_key_bindings(&cfg);
+    web::window().add_event_listener({
+        let cfg = cfg.clone();
+        let game_playing = game_playing.clone();
+        move |event: KeyDownEvent| {
+            let (keys, binding) = {
+                let cfg = cfg.borrow();
+                (cfg.keys.clone(), cfg.binding.clone())
+            };
+            if let Some(action) = binding.take() {
+                event.prevent_default();
+                keys.borrow_mut().bind(action, &event.code(), &event.key());
+                if let Err(e) = keys.borrow().save() {
+                    web::window().alert(format!("failed to save key bindings: {}", e).as_ref());
+                }
+                show_key_bindings(&cfg);
+            } else if !game_in_progress(game_playing.clone()) && !typing(&event) && !event.repeat()
+                && keys.borrow().action_for(&event.code(), &event.key()) == Some(Action::Restart) {
+                event.prevent_default();
+                let settings = cfg.borrow().game_settings();
+                start_game(&cfg, &game_playing, snake::SnakeGameLogic::new(settings), GameKind::Regular);
+            }
+        }
+    });
+    let button = document().query_selector("#reset-keys").unwrap().unwrap();
+    button.add_event_listener({
+        let cfg = cfg.clone();
+        move |_: ClickEvent| {
+            let keys = cfg.borrow().keys.clone();
+            *keys.borrow_mut() = KeyBindings::default();
+            if let Err(e) = keys.borrow().save() {
+                web::window().alert(format!("failed to save key bindings: {}", e).as_ref());
+            }
+            cfg.borrow().binding.set(None);
+            show_key_bindings(&cfg);
+        }
+    });
+
+    cfg.borrow().events.subscribe({
+        let tracker = cfg.borrow().achievements.clone();
+        move |event| {
+            let earned = tracker.borrow_mut().on_event(event);
+            if let Some(achievement) = earned {
+                award(&[achievement]);
+            }
+        }
+    });
+
     let button = document().query_selector("#start-button").unwrap().unwrap();
     button.add_event_listener({
         let cfg = cfg.clone();
         let game_playing = game_playing.clone();
         move |_: ClickEvent| {
-            let game_playing = game_playing.clone();
-            if !game_in_progress(game_playing.clone()) {
-                set_game_in_progress(game_playing.clone(), true);
-                run_snake_game(&cfg, move |res| {
-                    match res {
-                        Err(e) => {
-                            web::window().alert(e.as_ref());
-                        }
-                        Ok(r) => {
-                            let new_div = document().create_element("p").unwrap();
-                            new_div.set_text_content(format!("score: {}", r.apples_eaten).as_ref());
-                            web::document().query_selector("#scores").unwrap().unwrap().append_child(&new_div);
-                        }
-                    }
-                    set_game_in_progress(game_playing, false);
-                });
+            let settings = cfg.borrow().game_settings();
+            start_game(&cfg, &game_playing, snake::SnakeGameLogic::new(settings), GameKind::Regular);
+        }
+    });
+
+    let daily_button = document().query_selector("#daily-button").unwrap().unwrap();
+    daily_button.add_event_listener({
+        let cfg = cfg.clone();
+        let game_playing = game_playing.clone();
+        move |_: ClickEvent| {
+            let date = daily::today();
+            let game = snake::SnakeGameLogic::new(daily::settings_for(date));
+            start_game(&cfg, &game_playing, game, GameKind::Daily(date));
+        }
+    });
+    show_daily_history();
+
+    let puzzles = document().query_selector("#puzzles").unwrap().unwrap();
+    let show_puzzles = document().query_selector("#puzzles-button").unwrap().unwrap();
+    show_puzzles.add_event_listener({
+        move |_: ClickEvent| {
+            toggle_display(&puzzles);
+        }
+    });
+    show_puzzle_list(&cfg, &game_playing);
+
+    let resume_button = document().query_selector("#resume-button").unwrap().unwrap();
+    resume_button.add_event_listener({
+        let cfg = cfg.clone();
+        let game_playing = game_playing.clone();
+        move |_: ClickEvent| {
+            if let Some(session) = storage::load::<SavedSession>(SAVED_GAME_KEY) {
+                let game = snake::SnakeGameLogic::resume(session.game);
+                start_game(&cfg, &game_playing, game, session.kind);
             }
         }
     });
+    show_resume_button(storage::load::<SavedSession>(SAVED_GAME_KEY).is_some());
 
     let options = document().query_selector("#options").unwrap().unwrap();
     let show_options = document().query_selector("#options-button").unwrap().unwrap();
@@ -228,6 +1326,15 @@ fn main() {
     let option_canvas_height: InputElement = document().query_selector("#canvas-height").unwrap().unwrap().try_into().unwrap();
     let option_canvas_width: InputElement = document().query_selector("#canvas-width").unwrap().unwrap().try_into().unwrap();
     let option_height: InputElement = document().query_selector("#height").unwrap().unwrap().try_into().unwrap();
+    let option_mode: SelectElement = document().query_selector("#game-mode").unwrap().unwrap().try_into().unwrap();
+    let option_portals: InputElement = document().query_selector("#portals").unwrap().unwrap().try_into().unwrap();
+    let option_start_length: InputElement = document().query_selector("#start-length").unwrap().unwrap().try_into().unwrap();
+    let option_start_x: InputElement = document().query_selector("#start-x").unwrap().unwrap().try_into().unwrap();
+    let option_start_y: InputElement = document().query_selector("#start-y").unwrap().unwrap().try_into().unwrap();
+    let option_start_direction: SelectElement = document().query_selector("#start-direction").unwrap().unwrap().try_into().unwrap();
+    let option_growth: InputElement = document().query_selector("#growth-per-apple").unwrap().unwrap().try_into().unwrap();
+    let option_practice: InputElement = document().query_selector("#practice").unwrap().unwrap().try_into().unwrap();
+    let option_grid: SelectElement = document().query_selector("#grid").unwrap().unwrap().try_into().unwrap();
     let submit_options = document().query_selector("#submit-options").unwrap().unwrap();
     submit_options.add_event_listener({
         let canvas = canvas.clone();
@@ -235,14 +1342,145 @@ fn main() {
         move |_: ClickEvent| {
             let mut cfg = cfg.borrow_mut();
             cfg.frame_rate = get_value(&option_frame_rate);
-            cfg.game_frame_rate = get_value(&option_game_frame_rate);
-            cfg.width = get_value(&option_width);
-            cfg.height = get_value(&option_height);
+            let portals = match portal::parse_portals(option_portals.raw_value().as_ref()) {
+                Ok(p) => p,
+                Err(e) => {
+                    web::window().alert(format!("invalid portals: {}", e).as_ref());
+                    return;
+                }
+            };
+            // leaving both blank starts the snake in the middle
+            let start_position = match (option_start_x.raw_value().parse(), option_start_y.raw_value().parse()) {
+                (Ok(x), Ok(y)) => Some((x, y)),
+                _ => None,
+            };
+            let start_direction = match option_start_direction.value().as_ref().map(|d| d.as_str()) {
+                Some("up") => snake::MoveDirection::Up,
+                Some("down") => snake::MoveDirection::Down,
+                Some("left") => snake::MoveDirection::Left,
+                Some("up-left") => snake::MoveDirection::UpLeft,
+                Some("up-right") => snake::MoveDirection::UpRight,
+                Some("down-left") => snake::MoveDirection::DownLeft,
+                Some("down-right") => snake::MoveDirection::DownRight,
+                _ => snake::MoveDirection::Right,
+            };
+            let settings = snake::GameSettings {
+                width: get_value(&option_width),
+                height: get_value(&option_height),
+                frame_rate: get_value(&option_game_frame_rate),
+                mode: option_mode.value()
+                    .and_then(|m| GameMode::from_name(m.as_ref()))
+                    .unwrap_or(GameMode::Classic),
+                seed: 0,
+                portals,
+                start_length: get_value(&option_start_length),
+                start_position,
+                start_direction,
+                growth_per_apple: get_value(&option_growth),
+                practice: js! { return @{&option_practice}.checked; }.try_into().unwrap(),
+                grid: option_grid.value()
+                    .and_then(|g| Grid::from_name(g.as_ref()))
+                    .unwrap_or(Grid::Square),
+            };
+            match settings.check() {
+                Ok(()) => cfg.settings = settings,
+                Err(e) => web::window().alert(format!("invalid game options: {}", e).as_ref()),
+            }
             canvas.set_attribute("width", get_value(&option_canvas_width).to_string().as_str()).expect("failed to set canvas width");
             canvas.set_attribute("height", get_value(&option_canvas_height).to_string().as_str()).expect("failed to set canvas height");
         }
     });
 
+    let score_filter: SelectElement = document().query_selector("#score-filter").unwrap().unwrap().try_into().unwrap();
+    score_filter.add_event_listener({
+        let cfg = cfg.clone();
+        let game_playing = game_playing.clone();
+        let score_filter = score_filter.clone();
+        move |_: ChangeEvent| {
+            let configuration = score_filter.raw_value();
+            cfg.borrow().scores.borrow_mut().filter = if configuration.is_empty() { None } else { Some(configuration) };
+            show_scores(&cfg, &game_playing);
+        }
+    });
+    show_scores(&cfg, &game_playing);
+
+    // race the best saved game played with the current options
+    let race_button = document().query_selector("#race-button").unwrap().unwrap();
+    race_button.add_event_listener({
+        let cfg = cfg.clone();
+        let game_playing = game_playing.clone();
+        move |_: ClickEvent| {
+            let configuration = cfg.borrow().settings.configuration();
+            let table = saved_scores::load();
+            let best = table.bests().into_iter().find(|b| b.0 == configuration).map(|b| b.1.result.clone());
+            match best {
+                Some(best) => race_ghost(&cfg, &game_playing, &best),
+                None => web::window().alert(format!("no saved scores for {} yet", configuration).as_ref()),
+            }
+        }
+    });
+
+    let share_button = document().query_selector("#share-button").unwrap().unwrap();
+    share_button.add_event_listener({
+        let cfg = cfg.clone();
+        move |_: ClickEvent| {
+            let fragment = match cfg.borrow().last_result {
+                Some(ref r) => share::fragment(r),
+                None => return,
+            };
+            let link: InputElement = document().query_selector("#share-link").unwrap().unwrap().try_into().unwrap();
+            js! {
+                var link = @{&link};
+                link.value = location.origin + location.pathname + location.search + @{fragment};
+                link.style.display = "inline";
+                link.select();
+                if (navigator.clipboard) {
+                    navigator.clipboard.writeText(link.value);
+                }
+            }
+        }
+    });
+
+    let export_scores = document().query_selector("#export-scores").unwrap().unwrap();
+    export_scores.add_event_listener(move |_: ClickEvent| {
+        let table = saved_scores::load();
+        download_file("snake-scores.json", &replay::export_history(&table.entries));
+    });
+
+    // replay files can come from the file picker or get dropped anywhere on the page
+    let import_file = {
+        let cfg = cfg.clone();
+        let game_playing = game_playing.clone();
+        move |text: String| {
+            import_replay_file(&cfg, &game_playing, &text);
+        }
+    };
+    js! {
+        var import_file = @{import_file};
+        var read = function(file) {
+            if (!file) {
+                return;
+            }
+            var reader = new FileReader();
+            reader.onload = function() {
+                import_file(reader.result);
+            };
+            reader.readAsText(file);
+        };
+        var picker = document.querySelector("#import-file");
+        picker.addEventListener("change", function() {
+            read(picker.files[0]);
+            picker.value = "";
+        });
+        document.addEventListener("dragover", function(e) {
+            e.preventDefault();
+        });
+        document.addEventListener("drop", function(e) {
+            e.preventDefault();
+            read(e.dataTransfer.files[0]);
+        });
+    }
+
     let scores = document().query_selector("#scores").unwrap().unwrap();
     let show_scores = document().query_selector("#scores-button").unwrap().unwrap();
     show_scores.add_event_listener({
@@ -251,5 +1489,34 @@ fn main() {
         }
     });
 
+    let achievements_panel = document().query_selector("#achievements").unwrap().unwrap();
+    let show_achievements_button = document().query_selector("#achievements-button").unwrap().unwrap();
+    show_achievements_button.add_event_listener({
+        move |_: ClickEvent| {
+            toggle_display(&achievements_panel);
+        }
+    });
+    show_achievements();
+
+    let leaderboard_panel = document().query_selector("#leaderboard").unwrap().unwrap();
+    let show_leaderboard_button = document().query_selector("#leaderboard-button").unwrap().unwrap();
+    show_leaderboard_button.add_event_listener({
+        move |_: ClickEvent| {
+            toggle_display(&leaderboard_panel);
+            show_leaderboards();
+        }
+    });
+
+    // somebody shared a replay with us, go straight to watching it
+    let fragment = web::window().location().and_then(|l| l.hash().ok()).unwrap_or_default();
+    match share::parse_fragment(&fragment) {
+        Some(Ok(shared)) => match snake::SnakeGameLogic::replay_history(&shared.settings, &shared.history) {
+            Ok(game) => start_game(&cfg, &game_playing, game, GameKind::Replay),
+            Err(e) => web::window().alert(format!("can't watch this replay: {}", e).as_ref()),
+        },
+        Some(Err(e)) => web::window().alert(e.as_ref()),
+        None => {}
+    }
+
     event_loop();
 }
@@ -0,0 +1,53 @@
+// a pair of linked squares, going into one brings you out of the other
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PortalPair {
+    pub a: (i32, i32),
+    pub b: (i32, i32),
+}
+
+// parses portals in the form "x,y x,y; x,y x,y", one pair per semicolon
+pub fn parse_portals(text: &str) -> Result<Vec<PortalPair>, String> {
+    let mut portals = Vec::new();
+    for pair in text.split(';') {
+        let pair = pair.trim();
+        if pair.is_empty() {
+            continue;
+        }
+        let squares: Vec<&str> = pair.split_whitespace().collect();
+        if squares.len() != 2 {
+            return Err(format!("\"{}\" should be two squares like \"1,2 3,4\"", pair));
+        }
+        portals.push(PortalPair {
+            a: parse_square(squares[0])?,
+            b: parse_square(squares[1])?,
+        });
+    }
+    Ok(portals)
+}
+
+fn parse_square(text: &str) -> Result<(i32, i32), String> {
+    let coords: Vec<&str> = text.split(',').collect();
+    if coords.len() != 2 {
+        return Err(format!("\"{}\" should be a square like \"1,2\"", text));
+    }
+    let x = coords[0].trim().parse().map_err(|_| format!("\"{}\" is not a number", coords[0]))?;
+    let y = coords[1].trim().parse().map_err(|_| format!("\"{}\" is not a number", coords[1]))?;
+    Ok((x, y))
+}
+
+// every portal has to be on the board, and no square can be in more than one portal
+pub fn check_portals(portals: &[PortalPair], width: u32, height: u32) -> Result<(), String> {
+    let mut seen = Vec::new();
+    for pair in portals.iter() {
+        for square in [pair.a, pair.b].iter() {
+            if square.0 < 0 || square.0 >= width as i32 || square.1 < 0 || square.1 >= height as i32 {
+                return Err(format!("portal {:?} is off the board", square));
+            }
+            if seen.contains(square) {
+                return Err(format!("square {:?} is used by more than one portal", square));
+            }
+            seen.push(*square);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+use serde_json;
+use snake::{SnakeGameLogic, MoveDirection, GameEnd};
+use portal::{PortalPair, check_portals};
+
+// `PuzzlePack::parse` turns away packs with any other version, so static/puzzles.json
+// has to go up with it
+pub const PUZZLE_PACK_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PuzzleLimit {
+    Moves(u32),
+    Seconds(u32),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Puzzle {
+    pub id: String,
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    // game frames per second, i.e. the speed of the snake
+    pub frame_rate: u32,
+    // squares the snake starts on, from tail to head
+    pub body: Vec<(i32, i32)>,
+    pub direction: MoveDirection,
+    // apples in the order they appear, the next one shows up
+    // as soon as the previous one is eaten
+    pub apples: Vec<(i32, i32)>,
+    pub limit: PuzzleLimit,
+    #[serde(default)]
+    pub portals: Vec<PortalPair>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PuzzlePack {
+    pub version: u32,
+    pub name: String,
+    pub puzzles: Vec<Puzzle>,
+}
+
+impl PuzzlePack {
+    pub fn parse(json: &str) -> Result<PuzzlePack, String> {
+        let pack: PuzzlePack = serde_json::from_str(json).map_err(|e| format!("invalid puzzle pack: {}", e))?;
+        if pack.version != PUZZLE_PACK_VERSION {
+            return Err(format!("puzzle pack version {} is not supported, expected version {}", pack.version, PUZZLE_PACK_VERSION));
+        }
+        for puzzle in pack.puzzles.iter() {
+            puzzle.check().map_err(|e| format!("puzzle \"{}\" is invalid: {}", puzzle.id, e))?;
+        }
+        Ok(pack)
+    }
+
+    pub fn builtin() -> PuzzlePack {
+        PuzzlePack::parse(include_str!("../static/puzzles.json")).expect("built in puzzle pack is invalid")
+    }
+}
+
+impl Puzzle {
+    // make sure the puzzle describes a game that can actually be set up
+    fn check(&self) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(String::from("board must be at least one square wide and tall"));
+        }
+        if self.frame_rate == 0 {
+            return Err(String::from("frame rate must be positive"));
+        }
+        match self.limit {
+            PuzzleLimit::Moves(0) | PuzzleLimit::Seconds(0) => {
+                return Err(String::from("limit must be positive"));
+            }
+            _ => {}
+        }
+        if self.body.is_empty() {
+            return Err(String::from("snake needs at least one square"));
+        }
+        let mut seen = HashSet::new();
+        for (i, square) in self.body.iter().enumerate() {
+            if !self.in_bounds(*square) {
+                return Err(format!("snake square {:?} is off the board", square));
+            }
+            if !seen.insert(*square) {
+                return Err(format!("snake covers {:?} twice", square));
+            }
+            if i > 0 {
+                let prev = self.body[i - 1];
+                if (prev.0 - square.0).abs() + (prev.1 - square.1).abs() != 1 {
+                    return Err(format!("snake squares {:?} and {:?} are not next to each other", prev, square));
+                }
+            }
+        }
+        if self.body.len() > 1 {
+            let neck = self.body[self.body.len() - 2];
+            let head = self.body[self.body.len() - 1];
+            let facing = match (head.0 - neck.0, head.1 - neck.1) {
+                (0, -1) => MoveDirection::Up,
+                (0, 1) => MoveDirection::Down,
+                (-1, 0) => MoveDirection::Left,
+                _ => MoveDirection::Right,
+            };
+            if self.direction.opposite(facing) {
+                return Err(String::from("snake starts off heading back into itself"));
+            }
+        }
+        if self.apples.is_empty() {
+            return Err(String::from("puzzle needs at least one apple"));
+        }
+        for apple in self.apples.iter() {
+            if !self.in_bounds(*apple) {
+                return Err(format!("apple {:?} is off the board", apple));
+            }
+        }
+        if seen.contains(&self.apples[0]) {
+            return Err(String::from("first apple is underneath the snake"));
+        }
+        check_portals(&self.portals, self.width, self.height)?;
+        for pair in self.portals.iter() {
+            for square in [pair.a, pair.b].iter() {
+                if seen.contains(square) || self.apples.contains(square) {
+                    return Err(format!("portal {:?} is covered by the snake or an apple", square));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn in_bounds(&self, square: (i32, i32)) -> bool {
+        square.0 >= 0 && square.0 < self.width as i32 && square.1 >= 0 && square.1 < self.height as i32
+    }
+
+    // re-play a solution one turn at a time and make sure it eats every apple within the limit
+    pub fn validate(&self, solution: &[MoveDirection]) -> Result<(), String> {
+        let mut game = SnakeGameLogic::from_puzzle(self);
+        for direction in solution.iter() {
+            game.press_key(*direction);
+            if !game.tick() {
+                break;
+            }
+        }
+        match game.end() {
+            Some(GameEnd::Cleared) => Ok(()),
+            Some(GameEnd::Crashed) => Err(String::from("the snake crashed")),
+            Some(GameEnd::TimeUp) => Err(String::from("ran out of time")),
+            Some(GameEnd::OutOfMoves) => Err(String::from("ran out of moves")),
+            Some(GameEnd::Stopped) => Err(String::from("the game was ended early")),
+            None => Err(String::from("solution ended before every apple was eaten")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snake::MoveDirection::{Up, Down, Left, Right};
+
+    fn solution(id: &str) -> Vec<MoveDirection> {
+        match id {
+            "first-bite" => vec![Right, Right],
+            "around-the-corner" => vec![Right, Right, Right, Down, Down, Down, Down, Left, Left, Left],
+            "u-turn" => vec![Left, Up, Up],
+            "against-the-clock" => {
+                let mut moves = vec![Right; 5];
+                moves.extend(vec![Up; 5]);
+                moves.extend(vec![Left; 7]);
+                moves
+            }
+            "zig-zag" => vec![Right, Up, Up, Right, Down, Down, Down, Down, Right, Right, Right, Up, Up, Up, Up],
+            // through the portal at 3,2 and straight out of 6,0 onto the apple
+            "shortcut" => vec![Right, Right],
+            _ => panic!("no solution recorded for puzzle {}", id),
+        }
+    }
+
+    fn first_bite() -> Puzzle {
+        PuzzlePack::builtin().puzzles.into_iter().find(|p| p.id == "first-bite").unwrap()
+    }
+
+    fn check_fails(puzzle: &Puzzle, message: &str) {
+        match puzzle.check() {
+            Ok(()) => panic!("expected \"{}\" but the puzzle was accepted", message),
+            Err(e) => assert!(e.contains(message), "expected \"{}\" but got \"{}\"", message, e),
+        }
+    }
+
+    #[test]
+    fn builtin_pack_parses() {
+        let pack = PuzzlePack::builtin();
+        assert_eq!(pack.version, PUZZLE_PACK_VERSION);
+        assert!(!pack.puzzles.is_empty());
+    }
+
+    #[test]
+    fn every_builtin_puzzle_can_be_solved() {
+        for puzzle in PuzzlePack::builtin().puzzles.iter() {
+            let result = puzzle.validate(&solution(&puzzle.id));
+            assert!(result.is_ok(), "{}: {}", puzzle.id, result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn bad_solutions_dont_validate() {
+        let puzzle = first_bite();
+        assert_eq!(puzzle.validate(&[Right]), Err(String::from("solution ended before every apple was eaten")));
+        assert_eq!(puzzle.validate(&[Up, Up]), Err(String::from("the snake crashed")));
+        assert_eq!(puzzle.validate(&[Down, Down, Right, Up]), Err(String::from("ran out of moves")));
+    }
+
+    #[test]
+    fn packs_from_other_versions_are_rejected() {
+        let json = r#"{"version": 99, "name": "future", "puzzles": []}"#;
+        match PuzzlePack::parse(json) {
+            Err(e) => assert!(e.contains("version 99 is not supported"), "got \"{}\"", e),
+            Ok(_) => panic!("a version 99 pack was accepted"),
+        }
+        match PuzzlePack::parse("not json") {
+            Err(e) => assert!(e.contains("invalid puzzle pack"), "got \"{}\"", e),
+            Ok(_) => panic!("garbage was accepted as a pack"),
+        }
+    }
+
+    #[test]
+    fn invalid_puzzles_are_rejected() {
+        let mut puzzle = first_bite();
+        puzzle.width = 0;
+        check_fails(&puzzle, "at least one square wide");
+
+        let mut puzzle = first_bite();
+        puzzle.frame_rate = 0;
+        check_fails(&puzzle, "frame rate must be positive");
+
+        let mut puzzle = first_bite();
+        puzzle.limit = PuzzleLimit::Moves(0);
+        check_fails(&puzzle, "limit must be positive");
+
+        let mut puzzle = first_bite();
+        puzzle.body = vec![];
+        check_fails(&puzzle, "at least one square");
+
+        let mut puzzle = first_bite();
+        puzzle.body = vec![(-1, 1), (0, 1)];
+        check_fails(&puzzle, "off the board");
+
+        let mut puzzle = first_bite();
+        puzzle.body = vec![(1, 1), (2, 1), (1, 1)];
+        check_fails(&puzzle, "twice");
+
+        let mut puzzle = first_bite();
+        puzzle.body = vec![(0, 1), (2, 1)];
+        check_fails(&puzzle, "next to each other");
+
+        let mut puzzle = first_bite();
+        puzzle.direction = Left;
+        check_fails(&puzzle, "back into itself");
+
+        let mut puzzle = first_bite();
+        puzzle.apples = vec![];
+        check_fails(&puzzle, "at least one apple");
+
+        let mut puzzle = first_bite();
+        puzzle.apples = vec![(6, 1)];
+        check_fails(&puzzle, "off the board");
+
+        let mut puzzle = first_bite();
+        puzzle.apples = vec![(2, 1)];
+        check_fails(&puzzle, "underneath the snake");
+
+        let mut puzzle = first_bite();
+        puzzle.portals = vec![PortalPair { a: (0, 0), b: (9, 9) }];
+        check_fails(&puzzle, "portal (9, 9) is off the board");
+
+        let mut puzzle = first_bite();
+        puzzle.portals = vec![PortalPair { a: (0, 0), b: (5, 3) }, PortalPair { a: (5, 3), b: (0, 3) }];
+        check_fails(&puzzle, "more than one portal");
+
+        let mut puzzle = first_bite();
+        puzzle.portals = vec![PortalPair { a: (1, 1), b: (5, 3) }];
+        check_fails(&puzzle, "covered by the snake or an apple");
+
+        let mut puzzle = first_bite();
+        puzzle.portals = vec![PortalPair { a: (4, 1), b: (5, 3) }];
+        check_fails(&puzzle, "covered by the snake or an apple");
+    }
+}
@@ -0,0 +1,214 @@
+use serde_json;
+use snake::{SnakeGameLogic, GameResult, GameEnd, MAX_REPLAY_TURNS};
+use scores::ScoreEntry;
+
+// `parse` looks at this before anything else so a file from another version gets
+// a clear message instead of whatever serde trips over first
+pub const REPLAY_FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub enum ReplayContents {
+    // a single game to watch
+    Game(GameResult),
+    // a whole score table to merge in with ours
+    History(Vec<ScoreEntry>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    version: u32,
+    contents: ReplayContents,
+}
+
+// just enough to tell what version a file is before trying to read the rest of it
+#[derive(Deserialize)]
+struct ReplayFileHeader {
+    version: u32,
+}
+
+fn export(contents: ReplayContents) -> String {
+    serde_json::to_string(&ReplayFile {
+        version: REPLAY_FILE_VERSION,
+        contents,
+    }).expect("failed to serialize replay file")
+}
+
+pub fn export_game(result: &GameResult) -> String {
+    export(ReplayContents::Game(result.clone()))
+}
+
+pub fn export_history(entries: &[ScoreEntry]) -> String {
+    export(ReplayContents::History(entries.to_vec()))
+}
+
+// nothing from a file gets used until every game in it has been played back
+// and comes out the same as it says it did
+pub fn parse(text: &str) -> Result<ReplayContents, String> {
+    let header: ReplayFileHeader = serde_json::from_str(text)
+        .map_err(|_| String::from("this doesn't look like a snake replay file"))?;
+    if header.version != REPLAY_FILE_VERSION {
+        return Err(format!("replay file version {} is not supported, expected version {}", header.version, REPLAY_FILE_VERSION));
+    }
+    let file: ReplayFile = serde_json::from_str(text)
+        .map_err(|e| format!("replay file is corrupted: {}", e))?;
+    match file.contents {
+        ReplayContents::Game(ref result) => verify(result)?,
+        ReplayContents::History(ref entries) => {
+            for entry in entries.iter() {
+                verify(&entry.result).map_err(|e| format!("score from {} is invalid: {}", entry.date, e))?;
+            }
+        }
+    }
+    Ok(file.contents)
+}
+
+pub fn verify(result: &GameResult) -> Result<(), String> {
+    if result.turns_passed > MAX_REPLAY_TURNS {
+        return Err(format!("games can be at most {} turns long", MAX_REPLAY_TURNS));
+    }
+    let mut game = SnakeGameLogic::replay(result)?;
+    // the recording can't be longer than this anyway, but don't count on it
+    let mut turns = 0;
+    while turns <= MAX_REPLAY_TURNS && game.tick() {
+        turns += 1;
+    }
+    let replayed = game.get_results();
+
+    // everything the score table shows or sorts by has to match, not just the score
+    let mut differences = Vec::new();
+    {
+        let mut compare = |field: &str, replayed: String, claimed: String| {
+            if replayed != claimed {
+                differences.push(format!("{} {} but claims {}", field, replayed, claimed));
+            }
+        };
+        compare("apples", replayed.apples_eaten.to_string(), result.apples_eaten.to_string());
+        compare("turns", replayed.turns_passed.to_string(), result.turns_passed.to_string());
+        compare("score", replayed.score.to_string(), result.score.to_string());
+        compare("end", describe_end(replayed.end).to_string(), describe_end(result.end).to_string());
+        compare("size", format!("{}x{}", replayed.width, replayed.height), format!("{}x{}", result.width, result.height));
+        compare("speed", format!("{:?}", replayed.duration_between_frames), format!("{:?}", result.duration_between_frames));
+        compare("time played", format!("{:?}", replayed.time_played), format!("{:?}", result.time_played));
+    }
+    if !differences.is_empty() {
+        return Err(format!("replay finishes with {}", differences.join(", ")));
+    }
+    // there's no telling from the moves whether a game was rewound, but only practice games can be
+    if result.practice && !result.settings.practice {
+        return Err(String::from("only practice games can be rewound"));
+    }
+    Ok(())
+}
+
+fn describe_end(end: Option<GameEnd>) -> &'static str {
+    match end {
+        Some(GameEnd::Crashed) => "crashed",
+        Some(GameEnd::TimeUp) => "out of time",
+        Some(GameEnd::OutOfMoves) => "out of moves",
+        Some(GameEnd::Cleared) => "cleared",
+        Some(GameEnd::Stopped) => "stopped",
+        None => "unfinished",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use game_mode::GameMode;
+    use snake::{GameSettings, MoveDirection};
+
+    // sweeps the board row by row, the walls wrap around in zen so it never dies
+    fn play() -> GameResult {
+        let mut game = SnakeGameLogic::new(GameSettings {
+            mode: GameMode::Zen,
+            seed: 3,
+            ..GameSettings::default()
+        });
+        for turn in 0..200 {
+            match turn % 9 {
+                7 => game.press_key(MoveDirection::Down),
+                8 => game.press_key(MoveDirection::Right),
+                _ => {}
+            }
+            game.tick();
+        }
+        // zen games only ever end with the player stopping them
+        game.finish();
+        game.get_results()
+    }
+
+    #[test]
+    fn recorded_games_verify() {
+        let result = play();
+        assert!(result.apples_eaten > 0);
+        assert_eq!(verify(&result), Ok(()));
+    }
+
+    #[test]
+    fn exported_games_parse() {
+        let result = play();
+        match parse(&export_game(&result)) {
+            Ok(ReplayContents::Game(parsed)) => assert_eq!(parsed.score, result.score),
+            _ => panic!("exported game didn't parse"),
+        }
+    }
+
+    #[test]
+    fn stopped_games_verify() {
+        let mut game = SnakeGameLogic::new(GameSettings { mode: GameMode::Zen, seed: 3, ..GameSettings::default() });
+        for _ in 0..30 {
+            game.tick();
+        }
+        game.finish();
+        assert_eq!(verify(&game.get_results()), Ok(()));
+    }
+
+    #[test]
+    fn forged_results_dont_verify() {
+        let result = play();
+        assert!(verify(&GameResult { score: result.score + 1, ..result.clone() }).is_err());
+        assert!(verify(&GameResult { apples_eaten: result.apples_eaten + 1, ..result.clone() }).is_err());
+        assert!(verify(&GameResult { width: 100, height: 100, ..result.clone() }).is_err());
+        assert!(verify(&GameResult { end: Some(GameEnd::Cleared), ..result.clone() }).is_err());
+        assert!(verify(&GameResult { time_played: result.time_played * 2, ..result.clone() }).is_err());
+        assert!(verify(&GameResult { practice: true, ..result.clone() }).is_err());
+    }
+
+    #[test]
+    fn huge_boards_dont_verify() {
+        let mut result = play();
+        result.settings.width = 100_000;
+        result.settings.height = 100_000;
+        assert!(verify(&result).is_err());
+        let file = export_history(&[ScoreEntry { id: 0, date: NaiveDate::from_ymd(2018, 11, 3).and_hms(12, 0, 0), result }]);
+        assert!(parse(&file).is_err());
+    }
+
+    #[test]
+    fn restarted_replays_play_the_whole_game() {
+        let result = play();
+        let mut game = SnakeGameLogic::replay(&result).unwrap();
+        for _ in 0..50 {
+            game.tick();
+        }
+        let mut restarted = game.restart().unwrap();
+        while restarted.tick() {}
+        let replayed = restarted.get_results();
+        assert_eq!(replayed.apples_eaten, result.apples_eaten);
+        assert_eq!(replayed.turns_passed, result.turns_passed);
+    }
+
+    #[test]
+    fn restarted_races_bring_the_ghost_back_to_the_start() {
+        let result = play();
+        let mut race = SnakeGameLogic::race(&result).unwrap();
+        for _ in 0..50 {
+            race.tick();
+        }
+        let restarted = race.restart().unwrap();
+        assert_eq!(restarted.ghost_lead(), Some(0));
+        assert_eq!(restarted.get_results().turns_passed, 0);
+    }
+}
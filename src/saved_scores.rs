@@ -0,0 +1,49 @@
+use js_utils::get_date;
+use scores::{ScoreEntry, ScoreTable, SCORES_VERSION};
+use snake::GameResult;
+use storage;
+
+const SCORES_KEY: &str = "scores";
+
+// just enough to tell what version the stored table is before trying to read the rest of it
+#[derive(Deserialize)]
+struct StoredVersion {
+    version: u32,
+}
+
+// there's only been one version so far, scores from any other version are left where they
+// are rather than saved over, so nothing is lost if an older page gets opened again
+fn stored() -> Result<ScoreTable, String> {
+    match storage::load::<StoredVersion>(SCORES_KEY) {
+        Some(ref stored) if stored.version != SCORES_VERSION => {
+            Err(format!("scores were saved as version {}, this page only knows version {}", stored.version, SCORES_VERSION))
+        }
+        Some(_) => Ok(storage::load(SCORES_KEY).unwrap_or_else(ScoreTable::empty)),
+        None => Ok(ScoreTable::empty()),
+    }
+}
+
+// nothing to show for scores from another version, but they're still there
+pub fn load() -> ScoreTable {
+    stored().unwrap_or_else(|_| ScoreTable::empty())
+}
+
+pub fn record(result: GameResult) -> Result<(), String> {
+    let mut table = stored()?;
+    table.record(get_date().naive_utc(), result);
+    storage::save(SCORES_KEY, &table)
+}
+
+// returns how many of the scores were new
+pub fn import(entries: Vec<ScoreEntry>) -> Result<usize, String> {
+    let mut table = stored()?;
+    let imported = table.import(entries);
+    storage::save(SCORES_KEY, &table)?;
+    Ok(imported)
+}
+
+pub fn delete(id: u64) -> Result<(), String> {
+    let mut table = stored()?;
+    table.delete(id);
+    storage::save(SCORES_KEY, &table)
+}
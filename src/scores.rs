@@ -0,0 +1,218 @@
+use std::cmp::Ordering;
+use chrono::NaiveDateTime;
+use snake::GameResult;
+
+// bump this whenever the stored format changes, and teach `saved_scores` in the page how to upgrade the old one
+pub const SCORES_VERSION: u32 = 1;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub id: u64,
+    // utc
+    pub date: NaiveDateTime,
+    pub result: GameResult,
+}
+
+impl ScoreEntry {
+    pub fn configuration(&self) -> String {
+        self.result.configuration()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScoreTable {
+    pub version: u32,
+    next_id: u64,
+    pub entries: Vec<ScoreEntry>,
+}
+
+impl ScoreTable {
+    pub fn empty() -> Self {
+        ScoreTable {
+            version: SCORES_VERSION,
+            next_id: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, date: NaiveDateTime, result: GameResult) {
+        self.entries.push(ScoreEntry {
+            id: self.next_id,
+            date,
+            result,
+        });
+        self.next_id += 1;
+    }
+
+    // adds scores from somewhere else, skipping any we already have
+    //
+    // returns how many were new
+    pub fn import(&mut self, entries: Vec<ScoreEntry>) -> usize {
+        let mut imported = 0;
+        for entry in entries.into_iter() {
+            let duplicate = self.entries.iter()
+                .any(|e| e.date == entry.date && e.result.history == entry.result.history);
+            if duplicate {
+                continue;
+            }
+            self.entries.push(ScoreEntry {
+                id: self.next_id,
+                ..entry
+            });
+            self.next_id += 1;
+            imported += 1;
+        }
+        imported
+    }
+
+    pub fn delete(&mut self, id: u64) {
+        self.entries.retain(|e| e.id != id);
+    }
+
+    // the best entry for each configuration, in alphabetical order of configuration
+    pub fn bests(&self) -> Vec<(String, &ScoreEntry)> {
+        let mut bests: Vec<(String, &ScoreEntry)> = Vec::new();
+        for entry in self.entries.iter() {
+            let configuration = entry.configuration();
+            match bests.iter().position(|b| b.0 == configuration) {
+                Some(i) => {
+                    if entry.result.score > bests[i].1.result.score {
+                        bests[i].1 = entry;
+                    }
+                }
+                None => bests.push((configuration, entry)),
+            }
+        }
+        bests.sort_by(|a, b| a.0.cmp(&b.0));
+        bests
+    }
+
+    pub fn configurations(&self) -> Vec<String> {
+        self.bests().into_iter().map(|b| b.0).collect()
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum SortBy {
+    Date,
+    Score,
+    Size,
+    Speed,
+    Apples,
+    Turns,
+    Duration,
+}
+
+impl SortBy {
+    fn compare(self, a: &ScoreEntry, b: &ScoreEntry) -> Ordering {
+        match self {
+            SortBy::Date => a.date.cmp(&b.date),
+            SortBy::Score => a.result.score.cmp(&b.result.score),
+            SortBy::Size => (a.result.width * a.result.height).cmp(&(b.result.width * b.result.height)),
+            SortBy::Speed => a.result.settings.frame_rate.cmp(&b.result.settings.frame_rate),
+            SortBy::Apples => a.result.apples_eaten.cmp(&b.result.apples_eaten),
+            SortBy::Turns => a.result.turns_passed.cmp(&b.result.turns_passed),
+            SortBy::Duration => a.result.time_played.cmp(&b.result.time_played),
+        }
+    }
+}
+
+// how the score table is currently being looked at
+pub struct ScoreView {
+    pub sort: SortBy,
+    pub descending: bool,
+    // only show this configuration
+    pub filter: Option<String>,
+}
+
+impl ScoreView {
+    pub fn new() -> Self {
+        ScoreView {
+            sort: SortBy::Score,
+            descending: true,
+            filter: None,
+        }
+    }
+
+    // clicking the column that's already sorted flips it around
+    pub fn sort_by(&mut self, sort: SortBy) {
+        if self.sort == sort {
+            self.descending = !self.descending;
+        } else {
+            self.sort = sort;
+            self.descending = true;
+        }
+    }
+
+    pub fn apply<'a>(&self, table: &'a ScoreTable) -> Vec<&'a ScoreEntry> {
+        let mut entries: Vec<&ScoreEntry> = table.entries.iter()
+            .filter(|e| match self.filter {
+                Some(ref configuration) => e.configuration() == *configuration,
+                None => true,
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            let ordering = self.sort.compare(a, b);
+            if self.descending { ordering.reverse() } else { ordering }
+        });
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use game_mode::GameMode;
+    use snake::{GameSettings, MoveDirection, SnakeGameLogic};
+
+    fn settings() -> GameSettings {
+        GameSettings {
+            mode: GameMode::Zen,
+            seed: 3,
+            ..GameSettings::default()
+        }
+    }
+
+    fn play(settings: GameSettings) -> GameResult {
+        let mut game = SnakeGameLogic::new(settings);
+        for _ in 0..20 {
+            game.tick();
+        }
+        game.get_results()
+    }
+
+    fn date() -> NaiveDateTime {
+        NaiveDate::from_ymd(2018, 11, 3).and_hms(12, 0, 0)
+    }
+
+    #[test]
+    fn same_settings_share_a_best() {
+        let mut table = ScoreTable::empty();
+        table.record(date(), play(settings()));
+        table.record(date(), play(GameSettings { seed: 4, ..settings() }));
+        assert_eq!(table.bests().len(), 1);
+    }
+
+    #[test]
+    fn anything_that_changes_the_score_gets_its_own_best() {
+        let mut table = ScoreTable::empty();
+        table.record(date(), play(settings()));
+        table.record(date(), play(GameSettings { growth_per_apple: 0, ..settings() }));
+        table.record(date(), play(GameSettings { start_length: 3, ..settings() }));
+        table.record(date(), play(GameSettings { start_position: Some((1, 1)), ..settings() }));
+        table.record(date(), play(GameSettings { start_direction: MoveDirection::Down, ..settings() }));
+        table.record(date(), play(GameSettings { mode: GameMode::TimeAttack { seconds: 30 }, ..settings() }));
+        table.record(date(), play(GameSettings { mode: GameMode::TimeAttack { seconds: 60 }, ..settings() }));
+        assert_eq!(table.configurations().len(), 7);
+    }
+
+    #[test]
+    fn import_skips_scores_already_there() {
+        let mut table = ScoreTable::empty();
+        table.record(date(), play(settings()));
+        let entries = table.entries.clone();
+        assert_eq!(table.import(entries), 0);
+        assert_eq!(table.entries.len(), 1);
+    }
+}
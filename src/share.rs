@@ -0,0 +1,107 @@
+use bincode::{serialize, deserialize};
+use snake::{GameResult, GameSettings};
+
+// the first byte of every replay link, `parse_fragment` won't decode a link that starts
+// with anything else, so links people already shared stop working once this goes up
+const SHARE_VERSION: u8 = 1;
+// what comes before the replay in the url fragment
+pub const SHARE_PREFIX: &str = "#replay=";
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// everything needed to watch a game again, no need for the score since it gets re-simulated
+#[derive(Serialize, Deserialize)]
+pub struct SharedReplay {
+    pub settings: GameSettings,
+    pub history: Vec<u8>,
+}
+
+// the url fragment for a finished game, a version byte and then the replay
+pub fn fragment(result: &GameResult) -> String {
+    let shared = SharedReplay {
+        settings: result.settings.clone(),
+        history: result.history.clone(),
+    };
+    let mut bytes = vec![SHARE_VERSION];
+    bytes.extend(serialize(&shared).expect("failed to serialize shared replay"));
+    format!("{}{}", SHARE_PREFIX, encode(&bytes))
+}
+
+// None if the fragment isn't a replay at all, Err if it is one but it's broken
+pub fn parse_fragment(fragment: &str) -> Option<Result<SharedReplay, String>> {
+    if !fragment.starts_with(SHARE_PREFIX) {
+        return None;
+    }
+    Some(decode(&fragment[SHARE_PREFIX.len()..]).and_then(|bytes| {
+        match bytes.split_first() {
+            Some((&SHARE_VERSION, rest)) => deserialize(rest).map_err(|e| format!("replay link is corrupted: {}", e)),
+            Some((version, _)) => Err(format!("replay link version {} is not supported, expected version {}", version, SHARE_VERSION)),
+            None => Err(String::from("replay link is empty")),
+        }
+    }))
+}
+
+// url safe base64 without padding, so it can go straight in a link
+fn encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity((bytes.len() * 4 + 2) / 3);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        // 3 bytes make 4 characters, fewer bytes make fewer characters
+        for i in 0..chunk.len() + 1 {
+            text.push(BASE64_ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+        }
+    }
+    text
+}
+
+fn decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut n: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match BASE64_ALPHABET.iter().position(|&a| a == c) {
+            Some(v) => v as u32,
+            None => return Err(format!("replay link has an invalid character '{}'", c as char)),
+        };
+        n = (n << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((n >> bits) as u8);
+            n &= (1 << bits) - 1;
+        }
+    }
+    Ok(bytes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snake::SnakeGameLogic;
+
+    #[test]
+    fn base64_round_trips() {
+        for length in 0..8 {
+            let bytes: Vec<u8> = (0..length).map(|i| (i * 97 + 250) as u8).collect();
+            assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+        }
+        assert_eq!(encode(b"snake"), "c25ha2U");
+    }
+
+    #[test]
+    fn other_fragments_arent_replays() {
+        assert!(parse_fragment("#scores").is_none());
+        assert!(parse_fragment("#replay=AA").unwrap().is_err());
+    }
+
+    #[test]
+    fn links_to_huge_boards_dont_start() {
+        let mut result = SnakeGameLogic::new(GameSettings::default()).get_results();
+        result.settings.width = 100_000;
+        result.settings.height = 100_000;
+        let shared = parse_fragment(&fragment(&result)).unwrap().unwrap();
+        assert!(SnakeGameLogic::replay_history(&shared.settings, &shared.history).is_err());
+    }
+}
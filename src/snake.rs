@@ -1,14 +1,21 @@
 use std::io::Cursor;
-use std::io::Write;
-use brotli::CompressorWriter;
-use graphics_data::{GraphicsData};
-use bincode::serialize;
+use std::io::{Read, Write};
+use brotli::{CompressorWriter, Decompressor};
+use graphics_data::{GraphicsData, Paint, Sprite};
+use bincode::{serialize, deserialize};
 use chrono::{DateTime, FixedOffset};
 use time::Duration;
 use std::time::Duration as StdDuration;
 use std::ops::Sub;
 use std::collections::{LinkedList, HashSet};
-use js_utils::{get_date, random};
+use js_utils::get_date;
+use game_mode::GameMode;
+use rng::Rng;
+use puzzle::Puzzle;
+use portal::{PortalPair, check_portals};
+use events::{GameEvent, DeathCause, Item};
+use grid::Grid;
+use std::mem;
 
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
 struct Location {
@@ -22,16 +29,97 @@ pub enum MoveDirection {
     Down,
     Left,
     Right,
+    // only on grids that have them, see `Grid::directions`
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 impl MoveDirection {
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveDirection::Up => "up",
+            MoveDirection::Down => "down",
+            MoveDirection::Left => "left",
+            MoveDirection::Right => "right",
+            MoveDirection::UpLeft => "up-left",
+            MoveDirection::UpRight => "up-right",
+            MoveDirection::DownLeft => "down-left",
+            MoveDirection::DownRight => "down-right",
+        }
+    }
+
     pub fn opposite(self, other: MoveDirection) -> bool {
-        // hopefully the compiler simplifies this...
-        (self == MoveDirection::Up && other == MoveDirection::Down) ||
-            (self == MoveDirection::Down && other == MoveDirection::Up) ||
-            (self == MoveDirection::Left && other == MoveDirection::Right) ||
-            (self == MoveDirection::Right && other == MoveDirection::Left)
+        self.reverse() == other
+    }
+
+    // how far along the x and y axes one step in this direction goes on a square grid
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            MoveDirection::Up => (0, -1),
+            MoveDirection::Down => (0, 1),
+            MoveDirection::Left => (-1, 0),
+            MoveDirection::Right => (1, 0),
+            MoveDirection::UpLeft => (-1, -1),
+            MoveDirection::UpRight => (1, -1),
+            MoveDirection::DownLeft => (-1, 1),
+            MoveDirection::DownRight => (1, 1),
+        }
+    }
+
+    // a quarter turn, diagonals go to the diagonal a quarter turn round from them
+    pub fn clockwise(self) -> MoveDirection {
+        match self {
+            MoveDirection::Up => MoveDirection::Right,
+            MoveDirection::Right => MoveDirection::Down,
+            MoveDirection::Down => MoveDirection::Left,
+            MoveDirection::Left => MoveDirection::Up,
+            MoveDirection::UpRight => MoveDirection::DownRight,
+            MoveDirection::DownRight => MoveDirection::DownLeft,
+            MoveDirection::DownLeft => MoveDirection::UpLeft,
+            MoveDirection::UpLeft => MoveDirection::UpRight,
+        }
+    }
+
+    pub fn counter_clockwise(self) -> MoveDirection {
+        self.clockwise().reverse()
     }
+
+    pub fn rotated(self, rotation: Rotation) -> MoveDirection {
+        match rotation {
+            Rotation::Clockwise => self.clockwise(),
+            Rotation::CounterClockwise => self.counter_clockwise(),
+        }
+    }
+
+    pub fn reverse(self) -> MoveDirection {
+        match self {
+            MoveDirection::Up => MoveDirection::Down,
+            MoveDirection::Down => MoveDirection::Up,
+            MoveDirection::Left => MoveDirection::Right,
+            MoveDirection::Right => MoveDirection::Left,
+            MoveDirection::UpLeft => MoveDirection::DownRight,
+            MoveDirection::UpRight => MoveDirection::DownLeft,
+            MoveDirection::DownLeft => MoveDirection::UpRight,
+            MoveDirection::DownRight => MoveDirection::UpLeft,
+        }
+    }
+}
+
+// which way to turn relative to wherever the snake is heading
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+}
+
+// something waiting in the key buffer
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Steer {
+    Towards(MoveDirection),
+    // worked out from the heading when the tick comes around, not when it was pressed
+    Turn(Rotation),
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -39,40 +127,296 @@ enum CollisionType {
     Snake,
     Border,
     Apple,
+    Hazard,
     None,
 }
 
-#[derive(Serialize, Deserialize)]
+// why a game stopped
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GameEnd {
+    // ran into a wall, a hazard, or itself
+    Crashed,
+    TimeUp,
+    OutOfMoves,
+    // every apple was eaten, either the whole board is full or
+    // the puzzle has no apples left
+    Cleared,
+    // the player called it a day, the only way out of a zen game short of filling the board
+    Stopped,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameResult {
     pub apples_eaten: u32,
     pub turns_passed: u32,
-    // binary data representing all of the moves made during the game
-    // used for the unimplemented replay feature & potentially high
-    // score validation
+    // binary data representing all of the moves made during the game,
+    // played back to watch a replay and to check the score is real
     pub history: Vec<u8>,
     pub width: u32,
     pub height: u32,
     pub duration_between_frames: StdDuration,
+    pub score: u32,
+    pub end: Option<GameEnd>,
+    pub settings: GameSettings,
+    // the game was rewound at least once, so it doesn't count
+    pub practice: bool,
+    // how long the game actually ran for, not counting pauses
+    #[serde(default)]
+    pub time_played: StdDuration,
+}
+
+impl GameResult {
+    // the direction the snake went on every turn of the game
+    pub fn moves(&self) -> Result<Vec<MoveDirection>, String> {
+        decode_moves(&self.history)
+    }
+
+    pub fn configuration(&self) -> String {
+        self.settings.configuration()
+    }
+}
+
+// the biggest board anyone can play on, it's far more than fits on a screen and keeps
+// a board from a share link or a file from taking forever to set up
+pub const MAX_BOARD_SQUARES: u32 = 10_000;
+
+// the longest game a replay can hold, hours of play at any sensible speed
+pub const MAX_REPLAY_TURNS: u32 = 100_000;
+// a recorded turn takes up around 60 bytes once it's decompressed, this leaves plenty of room
+const MAX_HISTORY_BYTES: u64 = MAX_REPLAY_TURNS as u64 * 128;
+
+// turns compressed game history back into the moves it was made of
+//
+// a few bytes of brotli can decompress to gigabytes, so it gives up
+// rather than decompress more than any real game could need
+pub fn decode_moves(history: &[u8]) -> Result<Vec<MoveDirection>, String> {
+    let mut decompressed = Vec::new();
+    Decompressor::new(Cursor::new(history), 4096)
+        .take(MAX_HISTORY_BYTES + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| format!("failed to decompress game replay: {}", e))?;
+    if decompressed.len() as u64 > MAX_HISTORY_BYTES {
+        return Err(String::from("game replay is too long"));
+    }
+    let turns: Vec<GameTurn> = deserialize(decompressed.as_ref())
+        .map_err(|e| format!("failed to read game replay: {}", e))?;
+    if turns.len() > MAX_REPLAY_TURNS as usize {
+        return Err(String::from("game replay is too long"));
+    }
+    Ok(turns.into_iter().map(|t| t.next_direction).collect())
+}
+
+// everything needed to set up a game, the same settings will always
+// produce the same sequence of apples
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameSettings {
+    pub width: u32,
+    pub height: u32,
+    // game frames per second, i.e. the speed of the snake
+    pub frame_rate: u32,
+    pub mode: GameMode,
+    pub seed: u64,
+    pub portals: Vec<PortalPair>,
+    pub start_length: u32,
+    // where the head starts, the middle of the board if not set
+    pub start_position: Option<(i32, i32)>,
+    pub start_direction: MoveDirection,
+    // how many squares the snake grows by for each apple, one square per turn
+    pub growth_per_apple: u32,
+    // allows rewinding, at the cost of the score not counting
+    pub practice: bool,
+    #[serde(default)]
+    pub grid: Grid,
+}
+
+// a small classic game, what the options start out as
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            width: 8,
+            height: 6,
+            frame_rate: 4,
+            mode: GameMode::Classic,
+            seed: 0,
+            portals: Vec::new(),
+            start_length: 1,
+            start_position: None,
+            start_direction: MoveDirection::Right,
+            growth_per_apple: 1,
+            practice: false,
+            grid: Grid::Square,
+        }
+    }
+}
+
+impl GameSettings {
+    // the squares the snake starts on, from tail to head, in a straight
+    // line behind the head
+    pub fn starting_body(&self) -> Vec<(i32, i32)> {
+        let head = self.start_position.unwrap_or(((self.width / 2) as i32, (self.height / 2) as i32));
+        let mut body = vec![head];
+        for _ in 1..self.start_length.max(1) {
+            let behind = self.grid.step(body[body.len() - 1], self.start_direction.reverse());
+            body.push(behind);
+        }
+        body.reverse();
+        body
+    }
+
+    // games only compete with other games set up the same way, so everything that
+    // changes what a score means goes in here, the defaults are left out to keep it short
+    pub fn configuration(&self) -> String {
+        let mode = match self.mode {
+            GameMode::TimeAttack { seconds } => format!("{} {}s", self.mode.name(), seconds),
+            mode => mode.name().to_string(),
+        };
+        let grid = match self.grid {
+            Grid::Square => "",
+            Grid::EightWay => " eight-way",
+            Grid::Hex => " hex",
+        };
+        let mut configuration = format!("{}{} {}x{} @ {} fps", mode, grid, self.width, self.height, self.frame_rate);
+        if self.growth_per_apple != 1 {
+            configuration += &format!(", growth {}", self.growth_per_apple);
+        }
+        if self.start_length != 1 {
+            configuration += &format!(", length {}", self.start_length);
+        }
+        if let Some((x, y)) = self.start_position {
+            configuration += &format!(", start {},{}", x, y);
+        }
+        if self.start_direction != MoveDirection::Right {
+            configuration += &format!(", heading {}", self.start_direction.name());
+        }
+        if !self.portals.is_empty() {
+            let portals: Vec<String> = self.portals.iter()
+                .map(|p| format!("{},{} {},{}", p.a.0, p.a.1, p.b.0, p.b.1))
+                .collect();
+            configuration += &format!(", portals {}", portals.join("; "));
+        }
+        configuration
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(String::from("board must be at least one square wide and tall"));
+        }
+        if u64::from(self.width) * u64::from(self.height) > u64::from(MAX_BOARD_SQUARES) {
+            return Err(format!("boards can be at most {} squares", MAX_BOARD_SQUARES));
+        }
+        if self.frame_rate == 0 {
+            return Err(String::from("game frame rate must be positive"));
+        }
+        if self.start_length == 0 {
+            return Err(String::from("snake must start with at least one square"));
+        }
+        // checked before the body gets laid out, a huge length would take forever to build
+        if u64::from(self.start_length) > u64::from(self.width) * u64::from(self.height) {
+            return Err(String::from("snake is longer than the board has squares"));
+        }
+        // hex rows alternate which way they're pushed over, wrapping from the last row
+        // back to the first has to land on a row pushed over the other way
+        if self.grid == Grid::Hex && self.mode.wraps_edges() && self.height % 2 == 1 {
+            return Err(String::from("hex boards that wrap around need an even number of rows"));
+        }
+        if !self.grid.allows(self.start_direction) {
+            return Err(String::from("the snake can't start off heading that way on this grid"));
+        }
+        check_portals(&self.portals, self.width, self.height)?;
+        for square in self.starting_body().iter() {
+            if square.0 < 0 || square.0 >= self.width as i32 || square.1 < 0 || square.1 >= self.height as i32 {
+                return Err(String::from("snake doesn't fit on the board from that starting position"));
+            }
+            if self.portals.iter().any(|p| p.a == *square || p.b == *square) {
+                return Err(format!("snake starts on top of the portal at {:?}", square));
+            }
+        }
+        Ok(())
+    }
 }
 
 type PreviousMove = (Location, MoveDirection);
 
+// how many turns back a practice game can be rewound
+const MAX_REWIND_TURNS: usize = 50;
+// how many times faster turns go by while boosting
+const BOOST_FACTOR: i32 = 2;
+
+// everything that changes from one turn to the next, enough to put the game
+// back exactly the way it was
+#[derive(Clone, Serialize, Deserialize)]
+struct TurnState {
+    snake: LinkedList<PreviousMove>,
+    apple: Location,
+    last_direction: MoveDirection,
+    apples_eaten: u32,
+    turns_passed: u32,
+    grew_this_frame: bool,
+    pending_growth: u32,
+    hazards: Vec<Location>,
+    rng: Rng,
+    scripted_apples: Option<LinkedList<Location>>,
+    time_played: StdDuration,
+}
+
 pub struct SnakeGameLogic {
     width: u32,
     height: u32,
     snake: LinkedList<PreviousMove>,
     apple: Location,
     last_direction: MoveDirection,
-    key_buffer: LinkedList<MoveDirection>,
+    key_buffer: LinkedList<Steer>,
     last_frame: DateTime<FixedOffset>,
     duration_between_frames: Duration,
     history: LinkedList<GameTurn>,
     apples_eaten: u32,
     turns_passed: u32,
-    eaten_this_frame: bool
+    // the tail stays put on turns where the snake grows
+    grew_this_frame: bool,
+    growth_per_apple: u32,
+    // squares the snake still has to grow from apples it already ate
+    pending_growth: u32,
+    mode: GameMode,
+    hazards: Vec<Location>,
+    settings: GameSettings,
+    rng: Rng,
+    // apples still to come when they are scripted rather than random
+    scripted_apples: Option<LinkedList<Location>>,
+    end: Option<GameEnd>,
+    portals: Vec<(Location, Location)>,
+    // most recent turn last, only kept for practice games
+    rewind_points: LinkedList<TurnState>,
+    rewound: bool,
+    // how far into the current turn we were when the game was paused
+    paused_at: Option<Duration>,
+    // game time spent so far, which isn't the same as turns passed once the snake speeds up
+    time_played: StdDuration,
+    // everything that happened since the last time they were collected
+    events: Vec<GameEvent>,
+    // moves still to come when watching a replay instead of playing
+    replay_moves: Option<LinkedList<MoveDirection>>,
+    // a replay of an earlier game that plays alongside this one, turn for turn
+    ghost: Option<Box<SnakeGameLogic>>,
+    // only changes how fast turns go by in real time, so it isn't saved or replayed
+    boosting: bool,
+    // draw everything right where it is instead of sliding between squares
+    reduced_motion: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+// an unfinished game that can be stored away and picked back up later
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    settings: GameSettings,
+    state: TurnState,
+    history: LinkedList<GameTurn>,
+    rewound: bool,
+    #[serde(default)]
+    replay_moves: Option<LinkedList<MoveDirection>>,
+    #[serde(default)]
+    ghost: Option<Box<SavedGame>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct GameTurn {
     time: DateTime<FixedOffset>,
     snake: Location,
@@ -81,63 +425,466 @@ struct GameTurn {
 }
 
 impl SnakeGameLogic {
-    pub fn new(width: u32, height: u32, frame_rate: u32) -> Self {
+    pub fn new(settings: GameSettings) -> Self {
+        let direction = settings.start_direction;
+        let snake = settings.starting_body()
+            .into_iter()
+            .map(|(x, y)| (Location { x, y }, direction))
+            .collect();
+        SnakeGameLogic::with_snake(settings, snake, direction, None)
+    }
+
+    pub fn from_puzzle(puzzle: &Puzzle) -> Self {
+        let settings = GameSettings {
+            width: puzzle.width,
+            height: puzzle.height,
+            frame_rate: puzzle.frame_rate,
+            mode: GameMode::Puzzle { limit: puzzle.limit },
+            seed: 0,
+            portals: puzzle.portals.clone(),
+            start_length: puzzle.body.len() as u32,
+            start_position: puzzle.body.last().cloned(),
+            start_direction: puzzle.direction,
+            growth_per_apple: 1,
+            practice: false,
+            grid: Grid::Square,
+        };
+        let body: Vec<Location> = puzzle.body.iter().map(|&(x, y)| Location { x, y }).collect();
         let mut snake = LinkedList::new();
-        snake.push_back((Location { x: (width / 2) as i32, y: (height / 2) as i32 }, MoveDirection::Right));
+        for (i, loc) in body.iter().enumerate() {
+            // the tail didn't come from anywhere, pretend it was heading towards the rest of the body
+            let direction = if i > 0 {
+                direction_between(Grid::Square, &body[i - 1], loc)
+            } else if body.len() > 1 {
+                direction_between(Grid::Square, loc, &body[1])
+            } else {
+                puzzle.direction
+            };
+            snake.push_back((loc.clone(), direction));
+        }
+        let apples = puzzle.apples.iter().map(|&(x, y)| Location { x, y }).collect();
+        SnakeGameLogic::with_snake(settings, snake, puzzle.direction, Some(apples))
+    }
+
+    fn with_snake(settings: GameSettings, snake: LinkedList<PreviousMove>, direction: MoveDirection, scripted_apples: Option<LinkedList<Location>>) -> Self {
         let mut s = SnakeGameLogic {
-            width,
-            height,
+            width: settings.width,
+            height: settings.height,
             snake,
             apple: Location { x: 0, y: 0 },
-            last_direction: MoveDirection::Right,
+            last_direction: direction,
             key_buffer: LinkedList::new(),
-            duration_between_frames: Duration::milliseconds(((1.0 / frame_rate as f64) * 1000.0) as i64),
+            duration_between_frames: Duration::milliseconds(((1.0 / settings.frame_rate as f64) * 1000.0) as i64),
             last_frame: get_date(),
             history: LinkedList::new(),
             apples_eaten: 0,
             turns_passed: 0,
-            eaten_this_frame: false
+            grew_this_frame: false,
+            growth_per_apple: settings.growth_per_apple,
+            pending_growth: 0,
+            mode: settings.mode,
+            hazards: Vec::new(),
+            rng: Rng::new(settings.seed),
+            scripted_apples,
+            end: None,
+            portals: settings.portals.iter()
+                .map(|p| (Location { x: p.a.0, y: p.a.1 }, Location { x: p.b.0, y: p.b.1 }))
+                .collect(),
+            settings,
+            rewind_points: LinkedList::new(),
+            rewound: false,
+            paused_at: None,
+            time_played: StdDuration::from_secs(0),
+            events: Vec::new(),
+            replay_moves: None,
+            ghost: None,
+            boosting: false,
+            reduced_motion: false,
         };
         s.place_new_apple();
         s
     }
 
+    // plays a finished game back move by move, the snake ignores any keys pressed
+    pub fn replay(result: &GameResult) -> Result<Self, String> {
+        SnakeGameLogic::replay_history(&result.settings, &result.history)
+    }
+
+    pub fn replay_history(settings: &GameSettings, history: &[u8]) -> Result<Self, String> {
+        if let GameMode::Puzzle { .. } = settings.mode {
+            return Err(String::from("puzzle games can't be replayed"));
+        }
+        settings.check()?;
+        let moves = decode_moves(history)?;
+        let mut s = SnakeGameLogic::new(settings.clone());
+        s.replay_moves = Some(moves.into_iter().collect());
+        Ok(s)
+    }
+
+    // a new game on the same seed as an old one, with the old one playing along as a ghost
+    //
+    // rewinding would leave the ghost behind, so races are never practice games
+    pub fn race(ghost: &GameResult) -> Result<Self, String> {
+        let ghost_game = SnakeGameLogic::replay(ghost)?;
+        let mut s = SnakeGameLogic::new(GameSettings {
+            practice: false,
+            ..ghost.settings.clone()
+        });
+        s.ghost = Some(Box::new(ghost_game));
+        Ok(s)
+    }
+
+    // the same game again from the very start, no matter how far this one got or where
+    // it was picked back up from
+    //
+    // replays and ghosts get the moves they already played back in front of the ones to come,
+    // nothing for puzzles, their apples and starting body aren't in the settings so the game
+    // has to be set up from the puzzle again with `from_puzzle`
+    pub fn restart(&self) -> Option<Self> {
+        if let GameMode::Puzzle { .. } = self.settings.mode {
+            return None;
+        }
+        let mut s = SnakeGameLogic::new(self.settings.clone());
+        s.replay_moves = self.replay_moves.as_ref().map(|moves| {
+            self.history.iter()
+                .map(|turn| turn.next_direction)
+                .chain(moves.iter().cloned())
+                .collect()
+        });
+        // ghosts are always replays of regular games, never puzzles
+        s.ghost = self.ghost.as_ref().and_then(|g| g.restart()).map(Box::new);
+        Some(s)
+    }
+
+    // how many more apples the player has eaten than the ghost, if there is one
+    pub fn ghost_lead(&self) -> Option<i64> {
+        self.ghost.as_ref().map(|g| i64::from(self.apples_eaten) - i64::from(g.apples_eaten))
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.replay_moves.is_some()
+    }
+
     pub fn press_key(&mut self, direction: MoveDirection) {
-        self.key_buffer.push_back(direction);
+        // keys for directions this grid doesn't have do nothing
+        if self.settings.grid.allows(direction) {
+            self.key_buffer.push_back(Steer::Towards(direction));
+        }
+    }
+
+    pub fn turn(&mut self, rotation: Rotation) {
+        self.key_buffer.push_back(Steer::Turn(rotation));
+    }
+
+    // picks a saved game back up, paused so the player has time to get ready
+    pub fn resume(saved: SavedGame) -> Self {
+        let SavedGame { settings, state, history, rewound, replay_moves, ghost } = saved;
+        let mut s = SnakeGameLogic::with_snake(settings, LinkedList::new(), state.last_direction, None);
+        s.restore(state);
+        // the apple placed while setting up got replaced by the saved one, so nothing spawned
+        s.events.clear();
+        s.history = history;
+        s.rewound = rewound;
+        s.replay_moves = replay_moves;
+        s.ghost = ghost.map(|g| Box::new(SnakeGameLogic::resume(*g)));
+        s.paused_at = Some(Duration::zero());
+        s
+    }
+
+    pub fn save(&self) -> SavedGame {
+        SavedGame {
+            settings: self.settings.clone(),
+            state: self.turn_state(),
+            history: self.history.clone(),
+            rewound: self.rewound,
+            replay_moves: self.replay_moves.clone(),
+            ghost: self.ghost.as_ref().map(|g| Box::new(g.save())),
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        let now = get_date();
+        match self.paused_at.take() {
+            // pick up exactly where we left off in the turn
+            Some(elapsed) => self.last_frame = now - elapsed,
+            None => self.paused_at = Some(now - self.last_frame),
+        }
+    }
+
+    // ends the game right where it is, it still gets scored like any other
+    pub fn finish(&mut self) {
+        if self.end.is_none() {
+            self.end = Some(GameEnd::Stopped);
+        }
+    }
+
+    pub fn set_boost(&mut self, boosting: bool) {
+        self.boosting = boosting;
+    }
+
+    pub fn set_reduced_motion(&mut self, reduced_motion: bool) {
+        self.reduced_motion = reduced_motion;
+    }
+
+    // real time between turns
+    fn frame_duration(&self) -> Duration {
+        if self.boosting {
+            self.duration_between_frames / BOOST_FACTOR
+        } else {
+            self.duration_between_frames
+        }
+    }
+
+    // the board's width and height in squares
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn head(&self) -> (i32, i32) {
+        let head = &self.snake.back().unwrap().0;
+        (head.x, head.y)
+    }
+
+    pub fn grid(&self) -> Grid {
+        self.settings.grid
+    }
+
+    pub fn is_practice(&self) -> bool {
+        self.settings.practice
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
     }
 
     pub fn advance(&mut self) -> Result<GraphicsData, GraphicsData> {
+        // ended from outside a turn, maybe even while paused
+        if self.end.is_some() {
+            return Err(self.draw_screen(1.0));
+        }
+        if let Some(elapsed) = self.paused_at {
+            let progress = elapsed.num_microseconds().unwrap() as f64
+                / self.frame_duration().num_microseconds().unwrap() as f64;
+            return Ok(self.draw_screen(self.smoothed(progress.min(1.0))));
+        }
         let now = get_date();
         let mut time_diff: Duration = now - self.last_frame;
-        if time_diff >= self.duration_between_frames {
-            time_diff = time_diff.sub(self.duration_between_frames);
+        let frame_duration = self.frame_duration();
+        if time_diff >= frame_duration {
+            time_diff = time_diff.sub(frame_duration);
             self.last_frame = now;
-            self.last_direction = self.process_key_buffer();
-            self.turns_passed += 1;
-            self.record_turn();
-            self.eaten_this_frame = false;
-            let next = self.next_square();
-            match self.detect_collision(&next) {
-                CollisionType::Apple => {
-                    self.eaten_this_frame = true;
-                    self.apples_eaten += 1;
-                    self.snake.push_back((next, self.last_direction));
-                    self.place_new_apple();
+            if !self.tick() {
+                return Err(self.draw_screen(1.0));
+            }
+        }
+
+        // figure out how long we should wait before the next frame
+        let next_frame_progress = time_diff.num_microseconds().unwrap() as f64
+            / frame_duration.num_microseconds().unwrap() as f64;
+        Ok(self.draw_screen(self.smoothed(next_frame_progress)))
+    }
+
+    // with reduced motion everything sits still on its square until the next turn
+    fn smoothed(&self, progress: f64) -> f64 {
+        if self.reduced_motion {
+            1.0
+        } else {
+            progress
+        }
+    }
+
+    // play a single turn, independent of how much real time has passed
+    //
+    // returns false once the game is over
+    pub fn tick(&mut self) -> bool {
+        if self.end.is_some() {
+            return false;
+        }
+        if let Some(ref mut ghost) = self.ghost {
+            // the ghost just stops where it is once its game is over
+            ghost.tick();
+            ghost.drain_events();
+        }
+        if self.time_remaining() == Some(StdDuration::from_secs(0)) {
+            self.end = Some(GameEnd::TimeUp);
+            return false;
+        }
+        if self.moves_remaining() == Some(0) {
+            self.end = Some(GameEnd::OutOfMoves);
+            return false;
+        }
+        if let Some(ref mut moves) = self.replay_moves {
+            match moves.pop_front() {
+                Some(direction) => {
+                    self.key_buffer.clear();
+                    self.key_buffer.push_back(Steer::Towards(direction));
                 }
-                CollisionType::None => {
-                    self.snake.push_back((next, self.last_direction));
-                    self.lob_tail();
+                // the recording ran out before the game did, which only
+                // happens when the player stopped it there
+                None => {
+                    self.end = Some(GameEnd::Stopped);
+                    return false;
                 }
-                CollisionType::Snake | CollisionType::Border => {
-                    return Err(self.draw_screen(1.0));
+            }
+        }
+        if self.settings.practice {
+            self.save_rewind_point();
+        }
+        let previous_direction = self.last_direction;
+        self.last_direction = self.process_key_buffer();
+        if self.last_direction != previous_direction {
+            self.events.push(GameEvent::Turned { from: previous_direction, to: self.last_direction });
+        }
+        self.turns_passed += 1;
+        self.time_played += self.duration_between_frames.to_std().unwrap();
+        self.record_turn();
+        let head = self.snake.back().unwrap().0.clone();
+        let next = self.next_square();
+        let collision = if self.crosses_diagonal(&head, self.last_direction) {
+            CollisionType::Snake
+        } else {
+            self.detect_collision(&next)
+        };
+        match collision {
+            CollisionType::Apple => {
+                self.apples_eaten += 1;
+                self.events.push(GameEvent::AppleEaten { apples_eaten: self.apples_eaten });
+                self.pending_growth += self.growth_per_apple;
+                self.snake.push_back((next, self.last_direction));
+                self.place_new_apple();
+                if let Some(interval) = self.mode.speed_up_interval() {
+                    if self.apples_eaten % interval == 0 {
+                        self.update_speed();
+                        self.events.push(GameEvent::SpeedChanged { frame_rate: self.frame_rate() });
+                    }
                 }
             }
+            CollisionType::None => {
+                self.snake.push_back((next, self.last_direction));
+            }
+            collision @ CollisionType::Snake | collision @ CollisionType::Border | collision @ CollisionType::Hazard => {
+                if self.mode.collisions_kill() {
+                    let cause = match collision {
+                        CollisionType::Snake => DeathCause::Itself,
+                        CollisionType::Hazard => DeathCause::Hazard,
+                        _ => DeathCause::Wall,
+                    };
+                    self.events.push(GameEvent::Died(cause));
+                    self.end = Some(GameEnd::Crashed);
+                    return false;
+                }
+                // nothing can hurt us, just slither on through
+                self.snake.push_back((next, self.last_direction));
+            }
+        }
+        // growing happens one square per turn by leaving the tail where it is
+        self.grew_this_frame = self.pending_growth > 0;
+        if self.grew_this_frame {
+            self.pending_growth -= 1;
+            self.events.push(GameEvent::Grew { length: self.snake.len() as u32 });
+        } else {
+            self.lob_tail();
+        }
+        if let Some(interval) = self.mode.hazard_interval() {
+            if self.turns_passed % interval == 0 {
+                self.place_new_hazard();
+            }
         }
+        if self.end.is_none() && self.danger_ahead() {
+            self.events.push(GameEvent::DangerAhead);
+        }
+        self.end.is_none()
+    }
 
-        // figure out how long we should wait before the next frame
-        let next_frame_progress = time_diff.num_microseconds().unwrap() as f64
-            / self.duration_between_frames.num_microseconds().unwrap() as f64;
-        Ok(self.draw_screen(next_frame_progress))
+    // everything that happened since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    // game frames per second, this goes up as the snake speeds up
+    pub fn frame_rate(&self) -> f64 {
+        1_000_000.0 / self.duration_between_frames.num_microseconds().unwrap() as f64
+    }
+
+    fn update_speed(&mut self) {
+        let speed_ups = match self.mode.speed_up_interval() {
+            Some(interval) => self.apples_eaten / interval,
+            None => 0,
+        };
+        let frame_rate = self.settings.frame_rate + speed_ups;
+        self.duration_between_frames = Duration::milliseconds(((1.0 / frame_rate as f64) * 1000.0) as i64);
+    }
+
+    // go back up to `turns` turns, only allowed in practice games
+    //
+    // returns false if there was nothing to rewind
+    pub fn rewind(&mut self, turns: usize) -> bool {
+        if !self.settings.practice || self.end.is_some() {
+            return false;
+        }
+        let mut target = None;
+        for _ in 0..turns {
+            match self.rewind_points.pop_back() {
+                Some(point) => target = Some(point),
+                None => break,
+            }
+        }
+        let point = match target {
+            Some(point) => point,
+            None => return false,
+        };
+        self.restore(point);
+        // one turn is recorded per tick, so this drops everything after the rewind
+        while self.history.len() > self.turns_passed as usize {
+            self.history.pop_back();
+        }
+        self.key_buffer.clear();
+        self.last_frame = get_date();
+        self.rewound = true;
+        true
+    }
+
+    fn save_rewind_point(&mut self) {
+        let state = self.turn_state();
+        self.rewind_points.push_back(state);
+        if self.rewind_points.len() > MAX_REWIND_TURNS {
+            self.rewind_points.pop_front();
+        }
+    }
+
+    fn turn_state(&self) -> TurnState {
+        TurnState {
+            snake: self.snake.clone(),
+            apple: self.apple.clone(),
+            last_direction: self.last_direction,
+            apples_eaten: self.apples_eaten,
+            turns_passed: self.turns_passed,
+            grew_this_frame: self.grew_this_frame,
+            pending_growth: self.pending_growth,
+            hazards: self.hazards.clone(),
+            rng: self.rng.clone(),
+            scripted_apples: self.scripted_apples.clone(),
+            time_played: self.time_played,
+        }
+    }
+
+    fn restore(&mut self, state: TurnState) {
+        self.snake = state.snake;
+        self.apple = state.apple;
+        self.last_direction = state.last_direction;
+        self.apples_eaten = state.apples_eaten;
+        self.turns_passed = state.turns_passed;
+        self.grew_this_frame = state.grew_this_frame;
+        self.pending_growth = state.pending_growth;
+        self.hazards = state.hazards;
+        self.rng = state.rng;
+        self.scripted_apples = state.scripted_apples;
+        self.time_played = state.time_played;
+        self.update_speed();
+    }
+
+    pub fn end(&self) -> Option<GameEnd> {
+        self.end
     }
 
     pub fn get_results(&self) -> GameResult {
@@ -156,56 +903,104 @@ impl SnakeGameLogic {
             width: self.width,
             height: self.height,
             duration_between_frames: self.duration_between_frames.to_std().unwrap(),
+            score: self.mode.score(self.apples_eaten, self.turns_passed),
+            end: self.end,
+            settings: self.settings.clone(),
+            practice: self.rewound,
+            time_played: self.time_played,
         }
     }
 
+    // how many turns are left before the game ends, if there is a move limit
+    pub fn moves_remaining(&self) -> Option<u32> {
+        self.mode.move_limit().map(|limit| limit.saturating_sub(self.turns_passed))
+    }
+
+    // how much game time is left before the game ends, if there is a time limit
+    pub fn time_remaining(&self) -> Option<StdDuration> {
+        let played = self.time_played;
+        self.mode.time_limit().map(|limit| {
+            if played >= limit {
+                StdDuration::from_secs(0)
+            } else {
+                limit - played
+            }
+        })
+    }
+
     fn process_key_buffer(&mut self) -> MoveDirection {
         let mut next: MoveDirection = self.last_direction;
         let mut buffered: Option<MoveDirection> = None;
-        for key in self.key_buffer.iter() {
+        let mut waiting = LinkedList::new();
+        while let Some(key) = self.key_buffer.pop_front() {
+            let key = match key {
+                Steer::Towards(direction) => direction,
+                // two quick turns should come out as two turns, so once the snake is already
+                // turning this tick the rest of the buffer waits for the next one
+                Steer::Turn(rotation) => {
+                    if next != self.last_direction {
+                        waiting.push_back(key);
+                        waiting.append(&mut self.key_buffer);
+                        break;
+                    }
+                    self.settings.grid.turn(self.last_direction, rotation)
+                }
+            };
             if key.opposite(self.last_direction) {
-                buffered = Some(*key);
-            } else if *key != self.last_direction {
-                next = *key;
+                buffered = Some(key);
+            } else if key != self.last_direction {
+                next = key;
             }
-        };
-        self.key_buffer.clear();
+        }
         if let Some(key) = buffered {
             if next != self.last_direction {
-                self.key_buffer.push_back(key);
+                waiting.push_front(Steer::Towards(key));
             }
         }
+        self.key_buffer = waiting;
         next
     }
 
     fn next_square(&self) -> Location {
         let snake_head = &self.snake.back().unwrap().0;
-        match self.last_direction {
-            MoveDirection::Up => {
-                Location {
-                    x: snake_head.x,
-                    y: snake_head.y - 1,
-                }
-            }
-            MoveDirection::Down => {
-                Location {
-                    x: snake_head.x,
-                    y: snake_head.y + 1,
-                }
-            }
-            MoveDirection::Left => {
-                Location {
-                    x: snake_head.x - 1,
-                    y: snake_head.y,
-                }
+        let next = self.step(snake_head, self.last_direction);
+        // going into a portal brings us out the other side still heading the same way
+        match self.portal_partner(&next) {
+            Some(exit) => self.step(&exit, self.last_direction),
+            None => next,
+        }
+    }
+
+    // the square next to `from` in the given direction
+    fn step(&self, from: &Location, direction: MoveDirection) -> Location {
+        let next = neighbor(self.settings.grid, from, direction);
+        if self.mode.wraps_edges() {
+            Location {
+                x: (next.x + self.width as i32) % self.width as i32,
+                y: (next.y + self.height as i32) % self.height as i32,
             }
-            MoveDirection::Right => {
-                Location {
-                    x: snake_head.x + 1,
-                    y: snake_head.y,
-                }
+        } else {
+            next
+        }
+    }
+
+    fn portal_partner(&self, loc: &Location) -> Option<Location> {
+        for &(ref a, ref b) in self.portals.iter() {
+            if loc == a {
+                return Some(b.clone());
+            } else if loc == b {
+                return Some(a.clone());
             }
         }
+        None
+    }
+
+    // if a piece of the snake heading in `direction` just came out of a portal, this is
+    // the square it was on before it went in
+    fn came_through_portal(&self, loc: &Location, direction: MoveDirection) -> Option<Location> {
+        let grid = self.settings.grid;
+        let behind = neighbor(grid, loc, direction.reverse());
+        self.portal_partner(&behind).map(|entrance| neighbor(grid, &entrance, direction.reverse()))
     }
 
     fn detect_collision(&self, loc: &Location) -> CollisionType {
@@ -215,6 +1010,13 @@ impl SnakeGameLogic {
         if *loc == self.apple {
             return CollisionType::Apple;
         }
+        if self.hazards.contains(loc) {
+            return CollisionType::Hazard;
+        }
+        // we only end up on a portal square if another portal spat us out right in front of it
+        if self.portal_partner(loc).is_some() {
+            return CollisionType::Border;
+        }
         for snake_piece in self.snake.iter() {
             if *loc == snake_piece.0 {
                 return CollisionType::Snake;
@@ -223,65 +1025,198 @@ impl SnakeGameLogic {
         CollisionType::None
     }
 
+    // whether the next turn kills the snake unless it changes course
+    fn danger_ahead(&self) -> bool {
+        if !self.mode.collisions_kill() {
+            return false;
+        }
+        let head = self.snake.back().unwrap().0.clone();
+        if self.crosses_diagonal(&head, self.last_direction) {
+            return true;
+        }
+        match self.detect_collision(&self.next_square()) {
+            CollisionType::Snake | CollisionType::Border | CollisionType::Hazard => true,
+            CollisionType::Apple | CollisionType::None => false,
+        }
+    }
+
+    // whether heading diagonally out of `from` cuts between two pieces of the
+    // snake that are joined across that same corner
+    fn crosses_diagonal(&self, from: &Location, direction: MoveDirection) -> bool {
+        let (a, b) = match self.settings.grid.diagonal_corners(direction) {
+            Some((a, b)) => (self.step(from, a), self.step(from, b)),
+            None => return false,
+        };
+        self.snake.iter()
+            .zip(self.snake.iter().skip(1))
+            .any(|(p, q)| (p.0 == a && q.0 == b) || (p.0 == b && q.0 == a))
+    }
+
     fn lob_tail(&mut self) {
         self.snake.pop_front();
     }
 
     // 0 <= progress <= 1 :: represents how far into the next square we are
     fn draw_screen(&self, progress: f64) -> GraphicsData {
-        let mut graphics = GraphicsData::new(self.width, self.height);
+        let mut graphics = GraphicsData::new(self.width, self.height, self.settings.grid);
+        // only the ghost's snake, its apples would just get in the way
+        if let Some(ref ghost) = self.ghost {
+            let mut ghost_graphics = GraphicsData::new(self.width, self.height, self.settings.grid);
+            ghost.draw_snake(&mut ghost_graphics, progress);
+            graphics.ghost_pixels = ghost_graphics.pixels;
+        }
         // draw apple
-        graphics.add_pixel(self.apple.x as u32, self.apple.y as u32, String::from("red"));
+        graphics.add_pixel(self.apple.x as u32, self.apple.y as u32, Paint::Apple);
+        // draw hazards
+        for hazard in self.hazards.iter() {
+            graphics.add_pixel(hazard.x as u32, hazard.y as u32, Paint::Wall);
+        }
+
+        // draw portals
+        for (i, &(ref a, ref b)) in self.portals.iter().enumerate() {
+            graphics.add_pixel(a.x as u32, a.y as u32, Paint::Portal(i));
+            graphics.add_pixel(b.x as u32, b.y as u32, Paint::Portal(i));
+        }
 
-        let mut snake_iter = self.snake.iter().peekable();
-        while let Some(snake_piece) = snake_iter.next() {
+        self.draw_snake(&mut graphics, progress);
+        graphics
+    }
+
+    fn draw_snake(&self, graphics: &mut GraphicsData, progress: f64) {
+        let mut snake_iter = self.snake.iter().enumerate().peekable();
+        while let Some((i, snake_piece)) = snake_iter.next() {
             // skip the last piece
             if snake_iter.peek().is_some() {
                 let snake_piece = snake_piece.0.clone();
-                graphics.add_pixel(snake_piece.x as u32, snake_piece.y as u32, String::from("green"));
+                let paint = if i == 0 { Paint::Tail } else { Paint::Body };
+                graphics.add_pixel(snake_piece.x as u32, snake_piece.y as u32, paint);
             }
         }
 
-        if !self.eaten_this_frame {
+        match self.settings.grid {
+            Grid::Hex => {
+                self.draw_hex_ends(graphics, progress);
+                return;
+            }
+            Grid::EightWay => self.draw_diagonal_joins(graphics, progress),
+            Grid::Square => self.draw_sprites(graphics, progress),
+        }
+
+        if !self.grew_this_frame {
             let first_piece = self.snake.front().unwrap();
-            // cOmPoSiTiOn AnD dEcOmPoSiTiOn
-            let (c1, c2) = match first_piece.1 {
-                MoveDirection::Up => {
-                    ((0.0, 1.0 - progress), (1.0, 1.0))
-                },
-                MoveDirection::Down => {
-                    ((0.0, progress - 1.0), (1.0, 1.0))
-                },
-                MoveDirection::Left => {
-                    ((1.0 - progress, 0.0), (1.0, 1.0))
-                },
-                MoveDirection::Right => {
-                    ((progress - 1.0, 0.0), (1.0, 1.0))
-                },
-            };
             let first_loc = &first_piece.0;
-            // draw the sub pixel that shows our progress into the next square
-            graphics.add_sub_pixel(first_loc.x as u32, first_loc.y as u32, c1, c2, String::from("green"));
+            match self.came_through_portal(first_loc, first_piece.1) {
+                Some(before) => {
+                    // the tail is still on its way into the entrance portal
+                    let (c1, c2) = leaving_rect(first_piece.1, progress);
+                    graphics.add_sub_pixel(before.x as u32, before.y as u32, c1, c2, Paint::Tail);
+                }
+                None => {
+                    let (c1, c2) = sliding_rect(first_piece.1, progress);
+                    // draw the sub pixel that shows our progress into the next square
+                    graphics.add_sub_pixel(first_loc.x as u32, first_loc.y as u32, c1, c2, Paint::Tail);
+                }
+            }
         }
 
         let last_piece = &self.snake.back().unwrap().0;
-        let (c1, c2) = match self.last_direction {
-            MoveDirection::Up => {
-                ((0.0, 1.0 - progress), (1.0, 1.0))
-            },
-            MoveDirection::Down => {
-                ((0.0, progress - 1.0), (1.0, 1.0))
-            },
-            MoveDirection::Left => {
-                ((1.0 - progress, 0.0), (1.0, 1.0))
-            },
-            MoveDirection::Right => {
-                ((progress - 1.0, 0.0), (1.0, 1.0))
-            },
-        };
-        // draw the sub pixel that shows our progress out of the last square of the snake
-        graphics.add_sub_pixel(last_piece.x as u32, last_piece.y as u32, c1, c2, String::from("blue"));
-        graphics
+        match self.came_through_portal(last_piece, self.last_direction) {
+            Some(before) => {
+                // the head is split in two, half going into the entrance and half coming out of the exit
+                let entrance = neighbor(self.settings.grid, &before, self.last_direction);
+                let (c1, c2) = entering_rect(self.last_direction, progress);
+                graphics.add_sub_pixel(entrance.x as u32, entrance.y as u32, c1, c2, Paint::Head(self.last_direction));
+                graphics.add_sub_pixel(last_piece.x as u32, last_piece.y as u32, c1, c2, Paint::Head(self.last_direction));
+            }
+            None => {
+                let (c1, c2) = sliding_rect(self.last_direction, progress);
+                // draw the sub pixel that shows our progress out of the last square of the snake
+                graphics.add_sub_pixel(last_piece.x as u32, last_piece.y as u32, c1, c2, Paint::Head(self.last_direction));
+            }
+        }
+    }
+
+    // squares next to each other diagonally only touch at a corner, so each
+    // diagonal step gets a band from the middle of one square to the next
+    fn draw_diagonal_joins(&self, graphics: &mut GraphicsData, progress: f64) {
+        let grid = self.settings.grid;
+        let last = self.snake.len() - 1;
+        for (i, (from, to)) in self.snake.iter().zip(self.snake.iter().skip(1)).enumerate() {
+            let direction = to.1;
+            // wrapping around the edges or through a portal isn't a step across a corner
+            if grid.diagonal_corners(direction).is_none() || neighbor(grid, &from.0, direction) != to.0 {
+                continue;
+            }
+            let start = (from.0.x as f64, from.0.y as f64);
+            let mut end = (to.0.x as f64, to.0.y as f64);
+            if i + 1 == last {
+                // the head is only part of the way into its square
+                let (dx, dy) = direction.offset();
+                end = (start.0 + dx as f64 * progress, start.1 + dy as f64 * progress);
+            }
+            graphics.add_segment(start, end, Paint::Body);
+        }
+
+        // and the same for the bit of tail still sliding out of the square behind it
+        let &(ref tail, direction) = self.snake.front().unwrap();
+        if !self.grew_this_frame && grid.diagonal_corners(direction).is_some() && self.came_through_portal(tail, direction).is_none() {
+            let (dx, dy) = direction.offset();
+            let start = (tail.x as f64 - dx as f64 * (1.0 - progress), tail.y as f64 - dy as f64 * (1.0 - progress));
+            graphics.add_segment(start, (tail.x as f64, tail.y as f64), Paint::Tail);
+        }
+    }
+
+    // the same snake again as pieces that know which way they point, with the
+    // ends sliding along the same way the flat squares do
+    fn draw_sprites(&self, graphics: &mut GraphicsData, progress: f64) {
+        if self.snake.len() > 1 {
+            let &(ref tail, direction) = self.snake.front().unwrap();
+            let sliding = !self.grew_this_frame && self.came_through_portal(tail, direction).is_none();
+            if sliding {
+                // the tail slides out from underneath the piece in front of it,
+                // so it goes first and the body covers it up as it catches up
+                let (dx, dy) = direction.offset();
+                let behind = 1.0 - progress;
+                graphics.add_sprite(tail.x as f64 - dx as f64 * behind, tail.y as f64 - dy as f64 * behind, Sprite::Tail(direction));
+            } else {
+                let towards_body = self.snake.iter().nth(1).unwrap().1;
+                graphics.add_sprite(tail.x as f64, tail.y as f64, Sprite::Tail(towards_body));
+            }
+            let pieces = self.snake.iter().zip(self.snake.iter().skip(1));
+            for (piece, next) in pieces.skip(if sliding { 0 } else { 1 }) {
+                graphics.add_sprite(piece.0.x as f64, piece.0.y as f64, Sprite::Body { from: piece.1, to: next.1 });
+            }
+        }
+
+        let head = &self.snake.back().unwrap().0;
+        if self.came_through_portal(head, self.last_direction).is_some() {
+            graphics.add_sprite(head.x as f64, head.y as f64, Sprite::Head(self.last_direction));
+        } else {
+            let (dx, dy) = self.last_direction.offset();
+            let behind = 1.0 - progress;
+            graphics.add_sprite(head.x as f64 - dx as f64 * behind, head.y as f64 - dy as f64 * behind, Sprite::Head(self.last_direction));
+        }
+    }
+
+    // hexes can't be cut up into rectangles, so the head and tail are whole cells
+    // sliding across the edge between two cells instead
+    fn draw_hex_ends(&self, graphics: &mut GraphicsData, progress: f64) {
+        let grid = self.settings.grid;
+        if !self.grew_this_frame {
+            let &(ref first_loc, direction) = self.snake.front().unwrap();
+            if self.came_through_portal(first_loc, direction).is_none() {
+                let behind = neighbor(grid, first_loc, direction.reverse());
+                graphics.add_between(behind.x, behind.y, first_loc.x as u32, first_loc.y as u32, progress, Paint::Tail);
+            }
+        }
+
+        let last_piece = &self.snake.back().unwrap().0;
+        if self.came_through_portal(last_piece, self.last_direction).is_some() {
+            graphics.add_pixel(last_piece.x as u32, last_piece.y as u32, Paint::Head(self.last_direction));
+        } else {
+            let neck = neighbor(grid, last_piece, self.last_direction.reverse());
+            graphics.add_between(neck.x, neck.y, last_piece.x as u32, last_piece.y as u32, progress, Paint::Head(self.last_direction));
+        }
     }
 
     fn record_turn(&mut self) {
@@ -294,20 +1229,335 @@ impl SnakeGameLogic {
     }
 
     // this is a terrible solution but nothing better comes immediately to mind
-    fn place_new_apple(&mut self) {
-        let mut valid_locs: HashSet<Location> = HashSet::with_capacity(self.height as usize * self.width as usize * 2);
+    //
+    // the squares have to come out in the same order every time or the
+    // seeded apple placement stops being reproducible, so no iterating
+    // over a hash set here
+    fn free_locations(&self) -> Vec<Location> {
+        let mut taken: HashSet<Location> = HashSet::with_capacity(self.snake.len() + self.hazards.len() + 1);
+        taken.insert(self.apple.clone());
+        for s in self.snake.iter() {
+            taken.insert(s.0.clone());
+        }
+        for h in self.hazards.iter() {
+            taken.insert(h.clone());
+        }
+        for &(ref a, ref b) in self.portals.iter() {
+            taken.insert(a.clone());
+            taken.insert(b.clone());
+        }
+        let mut valid_locs = Vec::with_capacity(self.height as usize * self.width as usize);
         for y in 0..(self.height as i32) {
             for x in 0..(self.width as i32) {
-                valid_locs.insert(Location{x, y});
+                let loc = Location{x, y};
+                if !taken.contains(&loc) {
+                    valid_locs.push(loc);
+                }
             }
         }
-        valid_locs.remove(&self.apple);
-        for s in self.snake.iter() {
-            valid_locs.remove(&s.0);
+        valid_locs
+    }
+
+    fn place_new_apple(&mut self) {
+        if let Some(ref mut apples) = self.scripted_apples {
+            match apples.pop_front() {
+                Some(apple) => self.apple = apple,
+                None => self.end = Some(GameEnd::Cleared),
+            }
+        } else {
+            let valid_locs = self.free_locations();
+            if valid_locs.is_empty() {
+                // nowhere left to put an apple, the board is full
+                self.end = Some(GameEnd::Cleared);
+            } else {
+                self.apple = valid_locs[self.rng.below(valid_locs.len() as u32) as usize].clone();
+            }
         }
-        let valid_locs: Vec<Location> = valid_locs.into_iter().collect();
+        if self.end.is_none() {
+            self.events.push(GameEvent::ItemSpawned { item: Item::Apple, x: self.apple.x, y: self.apple.y });
+        }
+    }
+
+    fn place_new_hazard(&mut self) {
+        // don't drop a hazard right in front of the snake, that's just mean
+        let in_front = self.next_square();
+        let valid_locs: Vec<Location> = self.free_locations()
+            .into_iter()
+            .filter(|l| *l != in_front)
+            .collect();
         if !valid_locs.is_empty() {
-            self.apple = valid_locs[(random() * valid_locs.len() as f64) as usize].clone();
+            let hazard = valid_locs[self.rng.below(valid_locs.len() as u32) as usize].clone();
+            self.events.push(GameEvent::ItemSpawned { item: Item::Hazard, x: hazard.x, y: hazard.y });
+            self.hazards.push(hazard);
+        }
+    }
+}
+
+fn neighbor(grid: Grid, from: &Location, direction: MoveDirection) -> Location {
+    let (x, y) = grid.step((from.x, from.y), direction);
+    Location { x, y }
+}
+
+// cOmPoSiTiOn AnD dEcOmPoSiTiOn
+//
+// each of these works out one axis at a time, so diagonals come out right too
+
+// a whole square that has made it `progress` of the way into its square from the one behind it
+fn sliding_rect(direction: MoveDirection, progress: f64) -> ((f64, f64), (f64, f64)) {
+    let (dx, dy) = direction.offset();
+    ((-dx as f64 * (1.0 - progress), -dy as f64 * (1.0 - progress)), (1.0, 1.0))
+}
+
+// the part of a square that has been filled `progress` of the way from the edge it was entered from
+fn entering_rect(direction: MoveDirection, progress: f64) -> ((f64, f64), (f64, f64)) {
+    let axis = |d: i32| match d {
+        0 => (0.0, 1.0),
+        d if d > 0 => (0.0, progress),
+        _ => (1.0 - progress, progress),
+    };
+    let (dx, dy) = direction.offset();
+    let ((x, w), (y, h)) = (axis(dx), axis(dy));
+    ((x, y), (w, h))
+}
+
+// the part of a square that is still filled after leaving it `progress` of the way
+fn leaving_rect(direction: MoveDirection, progress: f64) -> ((f64, f64), (f64, f64)) {
+    let axis = |d: i32| match d {
+        0 => (0.0, 1.0),
+        d if d > 0 => (progress, 1.0 - progress),
+        _ => (0.0, 1.0 - progress),
+    };
+    let (dx, dy) = direction.offset();
+    let ((x, w), (y, h)) = (axis(dx), axis(dy));
+    ((x, y), (w, h))
+}
+
+// the direction to move in to get from one square to the one next to it
+fn direction_between(grid: Grid, from: &Location, to: &Location) -> MoveDirection {
+    grid.direction_between((from.x, from.y), (to.x, to.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use puzzle::PuzzlePack;
+
+    #[test]
+    fn replays_that_decompress_to_too_much_are_rejected() {
+        let mut history = Vec::new();
+        {
+            let mut writer = CompressorWriter::new(&mut history, 4096, 9, 22);
+            let zeros = vec![0; 1 << 20];
+            for _ in 0..16 {
+                writer.write_all(&zeros).unwrap();
+            }
         }
+        assert!(history.len() < 4096);
+        assert_eq!(decode_moves(&history).err(), Some(String::from("game replay is too long")));
+    }
+
+    #[test]
+    fn huge_boards_are_rejected() {
+        let settings = GameSettings {
+            width: 100_000,
+            height: 100_000,
+            ..GameSettings::default()
+        };
+        assert!(settings.check().is_err());
+        assert!(GameSettings { width: 100, height: 100, ..settings.clone() }.check().is_ok());
+        assert!(GameSettings { width: 101, height: 100, ..settings }.check().is_err());
+    }
+
+    #[test]
+    fn wrapping_hex_boards_come_back_across_the_edge() {
+        let settings = GameSettings {
+            mode: GameMode::Zen,
+            grid: Grid::Hex,
+            start_direction: MoveDirection::Right,
+            ..GameSettings::default()
+        };
+        assert!(GameSettings { height: 7, ..settings.clone() }.check().is_err());
+        assert!(settings.check().is_ok());
+
+        let game = SnakeGameLogic::new(settings);
+        for x in 0..8 {
+            let bottom = Location { x, y: 5 };
+            let over = game.step(&bottom, MoveDirection::DownRight);
+            assert!(over.y == 0);
+            assert!(game.step(&over, MoveDirection::UpLeft) == bottom);
+            let over = game.step(&bottom, MoveDirection::DownLeft);
+            assert!(game.step(&over, MoveDirection::UpRight) == bottom);
+        }
+    }
+
+    #[test]
+    fn puzzles_cant_restart_from_their_settings() {
+        let pack = PuzzlePack::builtin();
+        let mut game = SnakeGameLogic::from_puzzle(&pack.puzzles[0]);
+        game.tick();
+        assert!(game.restart().is_none());
+    }
+
+    #[test]
+    fn zen_games_end_when_the_player_stops() {
+        let mut game = SnakeGameLogic::new(GameSettings { mode: GameMode::Zen, ..GameSettings::default() });
+        for _ in 0..20 {
+            assert!(game.tick());
+        }
+        game.finish();
+        assert!(!game.tick());
+        assert!(game.advance().is_err());
+        let result = game.get_results();
+        assert!(result.end == Some(GameEnd::Stopped));
+        assert_eq!(result.turns_passed, 20);
+    }
+
+    #[test]
+    fn snakes_longer_than_the_board_are_rejected() {
+        let settings = GameSettings {
+            width: 4,
+            height: 4,
+            start_length: u32::max_value(),
+            ..GameSettings::default()
+        };
+        assert!(settings.check().is_err());
+    }
+
+    // sweeps across the board a row at a time so the snake eats whatever it runs into
+    fn sweep(game: &mut SnakeGameLogic, turns: u32) {
+        for _ in 0..turns {
+            match game.turns_passed % 9 {
+                7 => game.press_key(MoveDirection::Down),
+                8 => game.press_key(MoveDirection::Right),
+                _ => {}
+            }
+            game.tick();
+        }
+    }
+
+    fn state_of(game: &SnakeGameLogic) -> Vec<u8> {
+        serialize(&game.turn_state()).unwrap()
+    }
+
+    #[test]
+    fn rewinding_puts_everything_back_and_marks_the_game_as_practice() {
+        let settings = GameSettings { mode: GameMode::Zen, seed: 3, practice: true, ..GameSettings::default() };
+        let mut game = SnakeGameLogic::new(settings);
+        sweep(&mut game, 20);
+        let before = state_of(&game);
+        assert!(!game.get_results().practice);
+        sweep(&mut game, 12);
+        assert!(state_of(&game) != before);
+        assert!(game.rewind(12));
+        assert!(state_of(&game) == before);
+        assert_eq!(game.history.len(), 20);
+        assert!(game.get_results().practice);
+
+        let mut ranked = SnakeGameLogic::new(GameSettings { mode: GameMode::Zen, ..GameSettings::default() });
+        sweep(&mut ranked, 5);
+        assert!(!ranked.rewind(1));
+    }
+
+    #[test]
+    fn resumed_games_carry_on_like_they_were_never_stopped() {
+        let settings = GameSettings { mode: GameMode::Zen, seed: 3, ..GameSettings::default() };
+        let mut uninterrupted = SnakeGameLogic::new(settings.clone());
+        sweep(&mut uninterrupted, 80);
+
+        let mut game = SnakeGameLogic::new(settings);
+        sweep(&mut game, 35);
+        let saved = serde_json::to_string(&game.save()).unwrap();
+        let mut game = SnakeGameLogic::resume(serde_json::from_str(&saved).unwrap());
+        game.toggle_pause();
+        sweep(&mut game, 45);
+
+        assert!(state_of(&game) == state_of(&uninterrupted));
+        let (resumed, expected) = (game.get_results(), uninterrupted.get_results());
+        assert!(expected.apples_eaten > 0);
+        assert_eq!(resumed.apples_eaten, expected.apples_eaten);
+        assert_eq!(resumed.turns_passed, expected.turns_passed);
+        assert_eq!(resumed.score, expected.score);
+        assert_eq!(game.history.len(), uninterrupted.history.len());
+    }
+
+    #[test]
+    fn quick_relative_turns_come_out_one_per_tick() {
+        let mut game = SnakeGameLogic::new(GameSettings { mode: GameMode::Zen, ..GameSettings::default() });
+        game.turn(Rotation::Clockwise);
+        game.turn(Rotation::Clockwise);
+        game.tick();
+        assert!(game.last_direction == MoveDirection::Down);
+        game.tick();
+        assert!(game.last_direction == MoveDirection::Left);
+        game.tick();
+        assert!(game.last_direction == MoveDirection::Left);
+    }
+
+    fn eight_way_game(body: &[(i32, i32, MoveDirection)]) -> SnakeGameLogic {
+        let settings = GameSettings { grid: Grid::EightWay, ..GameSettings::default() };
+        let mut game = SnakeGameLogic::new(settings);
+        game.snake = body.iter().map(|&(x, y, direction)| (Location { x, y }, direction)).collect();
+        game.last_direction = body.last().unwrap().2;
+        game.apple = Location { x: 0, y: 5 };
+        game
+    }
+
+    #[test]
+    fn cutting_diagonally_through_the_body_is_a_crash() {
+        use self::MoveDirection::*;
+        // the body goes up and to the right from 2,3 to 3,2, so heading down and
+        // to the right from 2,2 squeezes between them
+        let mut game = eight_way_game(&[(2, 3, Right), (3, 2, UpRight), (3, 1, Up), (2, 2, DownLeft)]);
+        game.press_key(DownRight);
+        assert!(!game.tick());
+        assert!(game.end() == Some(GameEnd::Crashed));
+
+        // without the piece at 2,3 there's nothing to squeeze between
+        let mut game = eight_way_game(&[(3, 2, Up), (3, 1, Up), (2, 2, DownLeft)]);
+        game.press_key(DownRight);
+        assert!(game.tick());
+        assert!(game.snake.back().unwrap().0 == Location { x: 3, y: 3 });
+    }
+
+    fn portal_game(start: (i32, i32), direction: MoveDirection, portals: Vec<PortalPair>) -> SnakeGameLogic {
+        let settings = GameSettings {
+            start_position: Some(start),
+            start_direction: direction,
+            portals,
+            ..GameSettings::default()
+        };
+        assert!(settings.check().is_ok());
+        let mut game = SnakeGameLogic::new(settings);
+        game.apple = Location { x: 0, y: 5 };
+        game
+    }
+
+    fn head(game: &SnakeGameLogic) -> Location {
+        game.snake.back().unwrap().0.clone()
+    }
+
+    #[test]
+    fn portals_go_both_ways() {
+        let pair = PortalPair { a: (3, 2), b: (6, 0) };
+        let mut game = portal_game((1, 2), MoveDirection::Right, vec![pair]);
+        assert!(game.tick());
+        assert!(head(&game) == Location { x: 2, y: 2 });
+        // in at 3,2 and out the other side of 6,0, still heading right
+        assert!(game.tick());
+        assert!(head(&game) == Location { x: 7, y: 0 });
+
+        let mut game = portal_game((7, 0), MoveDirection::Left, vec![pair]);
+        assert!(game.tick());
+        assert!(head(&game) == Location { x: 2, y: 2 });
+    }
+
+    #[test]
+    fn portals_that_lead_straight_into_another_portal_are_a_wall() {
+        let portals = vec![PortalPair { a: (3, 2), b: (5, 0) }, PortalPair { a: (6, 0), b: (1, 5) }];
+        let mut game = portal_game((2, 2), MoveDirection::Right, portals);
+        assert!(game.danger_ahead());
+        assert!(!game.tick());
+        assert!(game.end() == Some(GameEnd::Crashed));
+        assert!(head(&game) == Location { x: 2, y: 2 });
     }
 }
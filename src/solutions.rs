@@ -0,0 +1,25 @@
+use std::collections::BTreeMap;
+use puzzle::Puzzle;
+use snake::MoveDirection;
+use storage;
+
+const SOLUTIONS_KEY: &str = "puzzle-solutions";
+
+fn load_solutions() -> BTreeMap<String, Vec<MoveDirection>> {
+    storage::load(SOLUTIONS_KEY).unwrap_or_else(BTreeMap::new)
+}
+
+// a puzzle only counts as solved if the stored solution still holds up
+pub fn is_solved(puzzle: &Puzzle) -> bool {
+    match load_solutions().get(&puzzle.id) {
+        Some(solution) => puzzle.validate(solution).is_ok(),
+        None => false,
+    }
+}
+
+pub fn save_solution(puzzle: &Puzzle, solution: Vec<MoveDirection>) -> Result<(), String> {
+    puzzle.validate(&solution)?;
+    let mut solutions = load_solutions();
+    solutions.insert(puzzle.id.clone(), solution);
+    storage::save(SOLUTIONS_KEY, &solutions)
+}
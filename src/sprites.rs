@@ -0,0 +1,131 @@
+use std::f64::consts::PI;
+use stdweb::web::CanvasRenderingContext2d;
+use stdweb::web::html_element::ImageElement;
+use graphics_data::{Sprite, SpriteData};
+use snake::MoveDirection;
+use storage;
+
+const SNAKE_STYLE_KEY: &str = "snake-style";
+const ATLAS_URL: &str = "snake-atlas.svg";
+
+// the atlas is a single row of square frames this many pixels across,
+// every one of them drawn heading right
+const FRAME_SIZE: f64 = 64.0;
+const HEAD_FRAME: f64 = 0.0;
+const STRAIGHT_FRAME: f64 = 1.0;
+// joins the left edge to the bottom edge
+const CORNER_FRAME: f64 = 2.0;
+const TAIL_FRAME: f64 = 3.0;
+
+// how the snake itself gets drawn, everything else on the board is the same either way
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SnakeStyle {
+    // squares in the theme's colors
+    Flat,
+    // pieces out of the sprite atlas, on square grids anyway
+    Sprites,
+}
+
+impl Default for SnakeStyle {
+    fn default() -> Self {
+        SnakeStyle::Flat
+    }
+}
+
+impl SnakeStyle {
+    pub fn from_name(name: &str) -> Option<SnakeStyle> {
+        match name {
+            "flat" => Some(SnakeStyle::Flat),
+            "sprites" => Some(SnakeStyle::Sprites),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SnakeStyle::Flat => "flat",
+            SnakeStyle::Sprites => "sprites",
+        }
+    }
+}
+
+pub fn load_style() -> SnakeStyle {
+    storage::load(SNAKE_STYLE_KEY).unwrap_or_default()
+}
+
+pub fn save_style(style: SnakeStyle) -> Result<(), String> {
+    storage::save(SNAKE_STYLE_KEY, &style)
+}
+
+pub struct SpriteAtlas {
+    image: ImageElement,
+}
+
+impl SpriteAtlas {
+    // starts the image loading, it gets used once it's there
+    pub fn load() -> Self {
+        let image = ImageElement::new();
+        image.set_src(ATLAS_URL);
+        SpriteAtlas { image }
+    }
+
+    // a broken image is complete too, it just doesn't have a size
+    pub fn ready(&self) -> bool {
+        self.image.complete() && self.image.natural_width().unwrap_or(0) > 0
+    }
+
+    pub fn draw(&self, ctx: &CanvasRenderingContext2d, data: &SpriteData, cell_width: f64, cell_height: f64) {
+        let (frame, turns) = frame_for(data.sprite);
+        // turned on its side a frame has to fill the cell the other way round
+        let (width, height) = if turns % 2 == 0 {
+            (cell_width, cell_height)
+        } else {
+            (cell_height, cell_width)
+        };
+        ctx.save();
+        ctx.translate((data.x + 0.5) * cell_width, (data.y + 0.5) * cell_height);
+        ctx.rotate(f64::from(turns) * PI / 2.0);
+        ctx.draw_image_s(self.image.clone(),
+                         frame * FRAME_SIZE, 0.0, FRAME_SIZE, FRAME_SIZE,
+                         -width / 2.0, -height / 2.0, width, height)
+            .expect("failed to draw from the sprite atlas");
+        ctx.restore();
+    }
+}
+
+// quarter turns clockwise from heading right, sprites are only drawn on
+// square grids so the diagonals never come up
+fn quarter_turns(direction: MoveDirection) -> u32 {
+    match direction {
+        MoveDirection::Right => 0,
+        MoveDirection::Down => 1,
+        MoveDirection::Left => 2,
+        MoveDirection::Up => 3,
+        _ => 0,
+    }
+}
+
+// which frame a sprite comes from and how far it has to be turned
+fn frame_for(sprite: Sprite) -> (f64, u32) {
+    match sprite {
+        Sprite::Head(direction) => (HEAD_FRAME, quarter_turns(direction)),
+        Sprite::Tail(direction) => (TAIL_FRAME, quarter_turns(direction)),
+        Sprite::Body { from, to } if from == to || from == to.reverse() => (STRAIGHT_FRAME, quarter_turns(to)),
+        Sprite::Body { from, to } => {
+            // a corner joins the edge the snake came in through to the one it goes out of,
+            // so turn the left and bottom edges of the frame until they line up with those
+            let edges = (from.reverse(), to);
+            let turns = (0..4)
+                .find(|&turns| {
+                    let (mut a, mut b) = (MoveDirection::Left, MoveDirection::Down);
+                    for _ in 0..turns {
+                        a = a.clockwise();
+                        b = b.clockwise();
+                    }
+                    (a, b) == edges || (b, a) == edges
+                })
+                .unwrap_or(0);
+            (CORNER_FRAME, turns)
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use stdweb::web;
+
+// anything we keep between visits goes through here as json in local storage
+
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    web::window().local_storage()
+        .get(key)
+        .and_then(|json| serde_json::from_str(json.as_ref()).ok())
+}
+
+pub fn remove(key: &str) {
+    web::window().local_storage().remove(key);
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string(value).map_err(|e| format!("failed to serialize {}: {}", key, e))?;
+    web::window().local_storage()
+        .insert(key, json.as_ref())
+        .map_err(|_| format!("failed to save {}, local storage is probably full", key))
+}
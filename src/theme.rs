@@ -0,0 +1,111 @@
+use serde_json;
+use storage;
+
+const THEME_KEY: &str = "theme";
+const CUSTOM_THEMES_KEY: &str = "custom-themes";
+
+// every color the board is drawn with, as anything css understands
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    pub background: String,
+    pub grid: String,
+    pub head: String,
+    pub body: String,
+    pub tail: String,
+    pub apple: String,
+    pub walls: String,
+    // the touch zone lines and the frame around the board
+    pub ui: String,
+    // one per portal pair so you can tell which go together, reused once there are more pairs
+    // than colors. themes saved before portals had colors get the light theme's
+    #[serde(default = "default_portals")]
+    pub portals: Vec<String>,
+}
+
+const LIGHT_PORTALS: [&str; 6] = ["purple", "orange", "deepskyblue", "magenta", "gold", "teal"];
+
+fn default_portals() -> Vec<String> {
+    LIGHT_PORTALS.iter().map(|c| c.to_string()).collect()
+}
+
+impl Theme {
+    pub fn portal(&self, pair_index: usize) -> String {
+        self.portals[pair_index % self.portals.len()].clone()
+    }
+}
+
+fn theme(name: &str, colors: [&str; 8], portals: &[&str]) -> Theme {
+    Theme {
+        name: name.to_string(),
+        background: colors[0].to_string(),
+        grid: colors[1].to_string(),
+        head: colors[2].to_string(),
+        body: colors[3].to_string(),
+        tail: colors[4].to_string(),
+        apple: colors[5].to_string(),
+        walls: colors[6].to_string(),
+        ui: colors[7].to_string(),
+        portals: portals.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+pub fn light() -> Theme {
+    theme("light", ["white", "grey", "blue", "green", "green", "red", "black", "black"], &LIGHT_PORTALS)
+}
+
+pub fn builtin() -> Vec<Theme> {
+    vec![
+        light(),
+        theme("dark", ["#1e1e1e", "#3c3c3c", "#4fc3f7", "#66bb6a", "#43a047", "#ef5350", "#9e9e9e", "#e0e0e0"],
+              &["#ce93d8", "#ffb74d", "#80deea", "#f48fb1", "#fff176", "#4db6ac"]),
+        theme("high contrast", ["black", "#555555", "white", "yellow", "#ffaa00", "#ff3030", "#00c8ff", "white"],
+              &["magenta", "orange", "lime", "#8080ff"]),
+        // blues against orange from the okabe-ito palette, nothing leans on telling red from green
+        theme("colorblind safe", ["white", "#bbbbbb", "#0072b2", "#56b4e9", "#9ad0f0", "#e69f00", "black", "black"],
+              &["#cc79a7", "#d55e00", "#009e73", "#f0e442"]),
+        theme("colorblind safe dark", ["#1e1e1e", "#3c3c3c", "#56b4e9", "#0072b2", "#005a8c", "#e69f00", "#f0e442", "#e0e0e0"],
+              &["#cc79a7", "#d55e00", "#009e73", "white"]),
+    ]
+}
+
+pub fn load_custom() -> Vec<Theme> {
+    storage::load(CUSTOM_THEMES_KEY).unwrap_or_default()
+}
+
+// the built in themes first, then any the player added
+pub fn all() -> Vec<Theme> {
+    let mut themes = builtin();
+    themes.extend(load_custom());
+    themes
+}
+
+// whichever theme was picked last, falling back to light if it's gone missing
+pub fn load_selected() -> Theme {
+    let name: Option<String> = storage::load(THEME_KEY);
+    name.and_then(|n| all().into_iter().find(|t| t.name == n))
+        .unwrap_or_else(light)
+}
+
+pub fn select(theme: &Theme) -> Result<(), String> {
+    storage::save(THEME_KEY, &theme.name)
+}
+
+// adds a theme from json shaped like `Theme`, replacing any custom theme with the same name
+pub fn add_custom(json: &str) -> Result<Theme, String> {
+    let theme: Theme = serde_json::from_str(json).map_err(|e| format!("that isn't a theme: {}", e))?;
+    if theme.name.trim().is_empty() {
+        return Err(String::from("themes need a name"));
+    }
+    if theme.portals.is_empty() {
+        return Err(String::from("themes need at least one portal color"));
+    }
+    if builtin().iter().any(|t| t.name == theme.name) {
+        return Err(format!("\"{}\" is one of the built in themes, pick another name", theme.name));
+    }
+    let mut custom = load_custom();
+    custom.retain(|t| t.name != theme.name);
+    custom.push(theme.clone());
+    storage::save(CUSTOM_THEMES_KEY, &custom)?;
+    Ok(theme)
+}
@@ -0,0 +1,143 @@
+use grid::Grid;
+use snake::MoveDirection;
+use storage;
+
+const TOUCH_SCHEME_KEY: &str = "touch-scheme";
+
+// how far a finger has to travel before it counts as a swipe, in css pixels
+const MIN_SWIPE_DISTANCE: f64 = 30.0;
+// and how fast, in css pixels per millisecond, so a slow drag doesn't turn the snake
+const MIN_SWIPE_VELOCITY: f64 = 0.3;
+
+// how tapping and dragging on the board steers
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TouchScheme {
+    // the board is split into a zone per direction, tap the one to go that way
+    Zones,
+    Swipe,
+    // tap somewhere and the snake heads that way from wherever its head is
+    Head,
+    // tap the left half of the board to turn left, the right half to turn right
+    Turns,
+}
+
+impl Default for TouchScheme {
+    fn default() -> Self {
+        TouchScheme::Swipe
+    }
+}
+
+impl TouchScheme {
+    pub fn from_name(name: &str) -> Option<TouchScheme> {
+        match name {
+            "zones" => Some(TouchScheme::Zones),
+            "swipe" => Some(TouchScheme::Swipe),
+            "head" => Some(TouchScheme::Head),
+            "turns" => Some(TouchScheme::Turns),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TouchScheme::Zones => "zones",
+            TouchScheme::Swipe => "swipe",
+            TouchScheme::Head => "head",
+            TouchScheme::Turns => "turns",
+        }
+    }
+}
+
+pub fn load_scheme() -> TouchScheme {
+    storage::load(TOUCH_SCHEME_KEY).unwrap_or_default()
+}
+
+pub fn save_scheme(scheme: TouchScheme) -> Result<(), String> {
+    storage::save(TOUCH_SCHEME_KEY, &scheme)
+}
+
+// where the current swipe started, in css pixels and milliseconds
+struct SwipeStart {
+    touch: i32,
+    x: f64,
+    y: f64,
+    time: f64,
+}
+
+// follows one finger around the board and picks swipes out of it
+//
+// each swipe starts where the last one finished, so a thumb can go
+// right, down, left without ever leaving the screen
+pub struct SwipeTracker {
+    start: Option<SwipeStart>,
+}
+
+impl SwipeTracker {
+    pub fn new() -> Self {
+        SwipeTracker { start: None }
+    }
+
+    // only the first finger down is followed, any others are ignored until it lifts
+    pub fn start(&mut self, touch: i32, x: f64, y: f64, time: f64) {
+        if self.start.is_none() {
+            self.start = Some(SwipeStart { touch, x, y, time });
+        }
+    }
+
+    pub fn move_to(&mut self, touch: i32, x: f64, y: f64, time: f64, grid: Grid) -> Option<MoveDirection> {
+        let (dx, dy, elapsed) = match self.start {
+            Some(ref start) if start.touch == touch => (x - start.x, y - start.y, time - start.time),
+            _ => return None,
+        };
+        let distance = dx.hypot(dy);
+        if distance < MIN_SWIPE_DISTANCE {
+            return None;
+        }
+        // too slow to be a swipe, but the next one gets measured from here
+        self.start = Some(SwipeStart { touch, x, y, time });
+        if distance / elapsed.max(1.0) < MIN_SWIPE_VELOCITY {
+            return None;
+        }
+        Some(grid.direction_towards(dx, dy))
+    }
+
+    pub fn end(&mut self, touch: i32) {
+        if self.start.as_ref().map(|s| s.touch) == Some(touch) {
+            self.start = None;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_swipes_steer() {
+        let mut swipe = SwipeTracker::new();
+        swipe.start(1, 100.0, 100.0, 0.0);
+        assert!(swipe.move_to(1, 110.0, 100.0, 10.0, Grid::Square) == None);
+        assert!(swipe.move_to(1, 150.0, 105.0, 50.0, Grid::Square) == Some(MoveDirection::Right));
+        // carries on from where the last swipe finished
+        assert!(swipe.move_to(1, 150.0, 165.0, 100.0, Grid::Square) == Some(MoveDirection::Down));
+    }
+
+    #[test]
+    fn slow_drags_dont_steer() {
+        let mut swipe = SwipeTracker::new();
+        swipe.start(1, 100.0, 100.0, 0.0);
+        assert!(swipe.move_to(1, 150.0, 100.0, 1000.0, Grid::Square) == None);
+    }
+
+    #[test]
+    fn only_the_first_finger_counts() {
+        let mut swipe = SwipeTracker::new();
+        swipe.start(1, 100.0, 100.0, 0.0);
+        swipe.start(2, 0.0, 0.0, 0.0);
+        assert!(swipe.move_to(2, 0.0, 100.0, 10.0, Grid::Square) == None);
+        swipe.end(1);
+        swipe.start(2, 0.0, 0.0, 20.0);
+        assert!(swipe.move_to(2, 0.0, 100.0, 30.0, Grid::Square) == Some(MoveDirection::Down));
+    }
+}